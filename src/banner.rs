@@ -0,0 +1,159 @@
+// run  := cargo run -- -c motd.toml
+// dir  := .
+// kid  :=
+
+// Hostname banners
+//
+// The bundled fonts share one 5 pixel high bitmap per character, covering what a hostname can
+// hold (letters, digits, '-', '.' and '_'), drawn three ways:
+//
+//     block    5 lines of full blocks
+//     small    3 lines of half blocks
+//     ascii    5 lines of '#', also used for block and small outside a UTF-8 locale
+//
+// Any other `font` value is read as the path of a FIGlet (.flf) font. Those are laid out at full
+// width, without FIGlet's smushing rules.
+
+use std::fs;
+
+use crate::utils::utf8_locale;
+
+const GLYPHS: [(char, [&str; 5]); 40] = [
+    ('a', [".###.", "#...#", "#####", "#...#", "#...#"]),
+    ('b', ["####.", "#...#", "####.", "#...#", "####."]),
+    ('c', [".####", "#....", "#....", "#....", ".####"]),
+    ('d', ["####.", "#...#", "#...#", "#...#", "####."]),
+    ('e', ["#####", "#....", "####.", "#....", "#####"]),
+    ('f', ["#####", "#....", "####.", "#....", "#...."]),
+    ('g', [".####", "#....", "#..##", "#...#", ".####"]),
+    ('h', ["#...#", "#...#", "#####", "#...#", "#...#"]),
+    ('i', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('j', ["..###", "...#.", "...#.", "#..#.", ".##.."]),
+    ('k', ["#...#", "#..#.", "###..", "#..#.", "#...#"]),
+    ('l', ["#....", "#....", "#....", "#....", "#####"]),
+    ('m', ["#...#", "##.##", "#.#.#", "#...#", "#...#"]),
+    ('n', ["#...#", "##..#", "#.#.#", "#..##", "#...#"]),
+    ('o', [".###.", "#...#", "#...#", "#...#", ".###."]),
+    ('p', ["####.", "#...#", "####.", "#....", "#...."]),
+    ('q', [".###.", "#...#", "#.#.#", "#..#.", ".##.#"]),
+    ('r', ["####.", "#...#", "####.", "#..#.", "#...#"]),
+    ('s', [".####", "#....", ".###.", "....#", "####."]),
+    ('t', ["#####", "..#..", "..#..", "..#..", "..#.."]),
+    ('u', ["#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('v', ["#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('w', ["#...#", "#...#", "#.#.#", "##.##", "#...#"]),
+    ('x', ["#...#", ".#.#.", "..#..", ".#.#.", "#...#"]),
+    ('y', ["#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('z', ["#####", "...#.", "..#..", ".#...", "#####"]),
+    ('0', [".###.", "#..##", "#.#.#", "##..#", ".###."]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["####.", "....#", ".###.", "#....", "#####"]),
+    ('3', ["####.", "....#", ".###.", "....#", "####."]),
+    ('4', ["#...#", "#...#", "#####", "....#", "....#"]),
+    ('5', ["#####", "#....", "####.", "....#", "####."]),
+    ('6', [".###.", "#....", "####.", "#...#", ".###."]),
+    ('7', ["#####", "....#", "...#.", "..#..", "..#.."]),
+    ('8', [".###.", "#...#", ".###.", "#...#", ".###."]),
+    ('9', [".###.", "#...#", ".####", "....#", ".###."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('.', [".", ".", ".", ".", "#"]),
+    ('_', [".....", ".....", ".....", ".....", "#####"]),
+    (' ', ["...", "...", "...", "...", "..."])
+];
+
+const UNKNOWN: [&str; 5] = ["###.", "...#", ".##.", "....", ".#.."];
+
+fn glyph(c: char) -> [&'static str; 5] {
+    let c = c.to_ascii_lowercase();
+    GLYPHS.iter().find(|(g, _)| *g == c).map(|(_, rows)| *rows).unwrap_or(UNKNOWN)
+}
+
+// The text as 5 rows of pixels, one blank column between characters.
+fn pixels(text: &str) -> Vec<String> {
+    let mut rows = vec![String::new(); 5];
+    for (i, c) in text.chars().enumerate() {
+        for (row, line) in rows.iter_mut().zip(glyph(c)) {
+            if i > 0 {
+                row.push('.');
+            }
+            row.push_str(line);
+        }
+    }
+    rows
+}
+
+fn render_pixels(text: &str, on: &str) -> Vec<String> {
+    pixels(text).iter().map(|row| row.replace('.', " ").replace('#', on)).collect()
+}
+
+// Two pixel rows per line using half blocks.
+fn render_small(text: &str) -> Vec<String> {
+    let rows = pixels(text);
+    let blank = ".".repeat(rows[0].len());
+    rows.chunks(2)
+        .map(|pair| {
+            let bottom = pair.get(1).unwrap_or(&blank);
+            pair[0]
+                .chars()
+                .zip(bottom.chars())
+                .map(|(top, bottom)| match (top == '#', bottom == '#') {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' '
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Glyphs of a FIGlet font for the printable ASCII range, as rows with hardblanks turned into spaces.
+fn parse_flf(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut lines = content.lines();
+    let header = lines.next().unwrap_or("");
+    if !header.starts_with("flf2a") || header.len() < 6 {
+        return Err("not a FIGlet font".to_string());
+    }
+    let hardblank = header[5..].chars().next().unwrap_or('$');
+    let fields: Vec<i64> = header.split_whitespace().skip(1).filter_map(|f| f.parse().ok()).collect();
+    let (height, comments) = match fields.as_slice() {
+        [height, _, _, _, comments, ..] if *height > 0 && *comments >= 0 => (*height as usize, *comments as usize),
+        _ => return Err("invalid FIGlet header".to_string())
+    };
+    let mut lines = lines.skip(comments);
+    let mut glyphs = Vec::new();
+    for _ in ' '..='~' {
+        let mut rows = Vec::with_capacity(height);
+        for _ in 0..height {
+            let line = lines.next().ok_or("FIGlet font ends early")?;
+            let endmark = line.chars().last().unwrap_or('@');
+            rows.push(line.trim_end_matches(endmark).replace(hardblank, " "));
+        }
+        glyphs.push(rows);
+    }
+    Ok(glyphs)
+}
+
+fn render_flf(text: &str, path: &str) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let glyphs = parse_flf(&content).map_err(|e| format!("{}: {}", path, e))?;
+    let height = glyphs[0].len();
+    let mut rows = vec![String::new(); height];
+    for c in text.chars().filter(|c| (' '..='~').contains(c)) {
+        for (row, line) in rows.iter_mut().zip(&glyphs[c as usize - ' ' as usize]) {
+            row.push_str(line);
+        }
+    }
+    Ok(rows)
+}
+
+pub fn render(text: &str, font: &str) -> Result<Vec<String>, String> {
+    let unicode = utf8_locale();
+    let rows = match font {
+        "block" if unicode => render_pixels(text, "█"),
+        "small" if unicode => render_small(text),
+        "block" | "small" | "ascii" => render_pixels(text, "#"),
+        path => render_flf(text, path)?
+    };
+    Ok(rows.into_iter().map(|row| row.trim_end().to_string()).collect())
+}
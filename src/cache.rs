@@ -0,0 +1,33 @@
+// run  := cargo run -- collect
+// dir  := .
+// kid  :=
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::load_config::Config;
+use crate::system_stats::SystemStats;
+use crate::utils::write_atomic;
+
+pub fn write_cache(path: &str, stats: &SystemStats) -> io::Result<()> {
+    let content = serde_json::to_string(stats).map_err(io::Error::other)?;
+    write_atomic(Path::new(path), &content)
+}
+
+// The snapshot if it is fresh and was collected with this config, None to collect live.
+pub fn read_cache(config: &Config) -> Option<SystemStats> {
+    let content = fs::read_to_string(&config.cache.path).ok()?;
+    let mut stats: SystemStats = serde_json::from_str(&content).ok()?;
+    if snapshot_age(&stats) > config.cache.max_age || stats.config != config.identity {
+        return None;
+    }
+    stats.cached = true;
+    Some(stats)
+}
+
+pub fn snapshot_age(stats: &SystemStats) -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    now.saturating_sub(stats.timestamp)
+}
@@ -0,0 +1,330 @@
+// run  := cargo run --
+// dir  := .
+// kid  :=
+
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{set_deadline, CollectError, SectionStatus};
+use crate::load_config::{Config, MemoryDetails, StateThreshold, SysDisk, SysDocker, SysGpu, SysService, SysVm, Threshold, Timeouts};
+use crate::system_stats::{
+    get_cpu_info, get_cpu_temp, get_cpu_usage, get_disks, get_docker, get_gpus, get_last_login, get_load, get_memory,
+    get_service_all, get_swaps, get_uptime, get_vms, get_zram, CpuInfo, CpuStats, DiskInfo, Docker, GpuInfo, LoadAvgInfo,
+    LoginInfo, MemInfo, Service, TempInfo, VmInfo
+};
+use crate::thresholds::percent;
+
+const KILL_MARGIN: Duration = Duration::from_millis(50);
+
+pub trait Collector: Send + 'static {
+    type Output: Default + Send + 'static;
+
+    // Key of the collected data in `SystemStats` and its status map.
+    fn name(&self) -> &str;
+
+    // Key of the config section this collector reads, also used for `[timeouts]`.
+    fn section(&self) -> &str;
+
+    fn timeout(&self, timeouts: &Timeouts) -> Duration { timeouts.for_section(self.section()) }
+
+    fn collect(&self) -> (Self::Output, SectionStatus);
+}
+
+pub fn section<T: Default>(result: Result<T, CollectError>) -> (T, SectionStatus) {
+    match result {
+        Ok(value) => (value, SectionStatus::Ok),
+        Err(e) => (T::default(), SectionStatus::from(&e))
+    }
+}
+
+pub fn partial<T>((value, error): (T, Option<CollectError>)) -> (T, SectionStatus) {
+    (value, error.as_ref().map(SectionStatus::from).unwrap_or_default())
+}
+
+pub struct Pending<T> {
+    name:    String,
+    timeout: Duration,
+    rx:      Receiver<(T, SectionStatus)>
+}
+
+pub fn spawn<C: Collector>(collector: C, timeouts: &Timeouts) -> Pending<C::Output> {
+    let (tx, rx) = mpsc::channel();
+    let (name, timeout) = (collector.name().to_string(), collector.timeout(timeouts));
+    // Commands run by the collector are killed a little before `wait` gives up, so they are gone
+    // even when the process exits right after a timeout.
+    let deadline = Instant::now() + timeout.saturating_sub(KILL_MARGIN);
+    thread::spawn(move || {
+        set_deadline(deadline);
+        let _ = tx.send(collector.collect());
+    });
+    Pending { name, timeout, rx }
+}
+
+impl<T: Default> Pending<T> {
+    pub fn name(&self) -> &str { &self.name }
+
+    pub fn wait(self, start: Instant) -> (T, SectionStatus) {
+        let deadline = start + self.timeout;
+        self.rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .unwrap_or_else(|_| (T::default(), SectionStatus::TimedOut))
+    }
+}
+
+pub struct MemoryCollector {
+    pub memory:  Threshold,
+    pub swap:    Threshold,
+    pub details: MemoryDetails
+}
+
+impl MemoryCollector {
+    pub fn new(config: &Config) -> Self {
+        MemoryCollector { memory: config.thresholds.memory, swap: config.thresholds.swap, details: config.memory_details }
+    }
+}
+
+impl Collector for MemoryCollector {
+    type Output = MemInfo;
+
+    fn name(&self) -> &str { "memory" }
+
+    fn section(&self) -> &str { "memory" }
+
+    fn collect(&self) -> (MemInfo, SectionStatus) {
+        let (mut memory, status) = section(get_memory());
+        memory.level = self.memory.level(percent(memory.total_mem - memory.available_mem, memory.total_mem));
+        memory.swap_level = self.swap.level(percent(memory.total_swap - memory.free_swap, memory.total_swap));
+        if self.details.zram {
+            memory.details.zram = get_zram();
+        }
+        if self.details.swap_devices {
+            memory.details.swaps = get_swaps();
+        }
+        (memory, status)
+    }
+}
+
+pub struct LoadCollector {
+    pub threshold: Threshold
+}
+
+impl LoadCollector {
+    pub fn new(config: &Config) -> Self { LoadCollector { threshold: config.thresholds.load } }
+}
+
+impl Collector for LoadCollector {
+    type Output = LoadAvgInfo;
+
+    fn name(&self) -> &str { "load_avg" }
+
+    fn section(&self) -> &str { "cpuload" }
+
+    fn collect(&self) -> (LoadAvgInfo, SectionStatus) {
+        let (mut load_avg, status) = section(get_load());
+        // Load thresholds are per CPU, so 1.0 means every CPU is busy.
+        let level = |load: f64| self.threshold.level(load_avg.per_cpu(load) / 100.0);
+        (load_avg.one_level, load_avg.five_level, load_avg.fifteen_level) = (level(load_avg.one), level(load_avg.five), level(load_avg.fifteen));
+        (load_avg, status)
+    }
+}
+
+pub struct TempCollector {
+    pub pattern:   String,
+    pub threshold: Threshold
+}
+
+impl TempCollector {
+    pub fn new(config: &Config) -> Self { TempCollector { pattern: config.cputemp.clone(), threshold: config.thresholds.temp } }
+}
+
+impl Collector for TempCollector {
+    type Output = Vec<TempInfo>;
+
+    fn name(&self) -> &str { "cpu_temp" }
+
+    fn section(&self) -> &str { "cputemp" }
+
+    fn collect(&self) -> (Vec<TempInfo>, SectionStatus) {
+        let (mut temps, status) = section(get_cpu_temp(&self.pattern));
+        for temp in &mut temps {
+            temp.level = self.threshold.level(temp.temp);
+        }
+        (temps, status)
+    }
+}
+
+pub struct CpuCollector {
+    pub interval:  Duration,
+    pub threshold: Threshold
+}
+
+impl CpuCollector {
+    pub fn new(config: &Config) -> Self {
+        CpuCollector { interval: Duration::from_millis(config.cpu.interval), threshold: config.thresholds.cpu }
+    }
+}
+
+impl Collector for CpuCollector {
+    type Output = CpuStats;
+
+    fn name(&self) -> &str { "cpu" }
+
+    fn section(&self) -> &str { "cpu" }
+
+    fn collect(&self) -> (CpuStats, SectionStatus) {
+        let (mut cpu, status) = section(get_cpu_usage(self.interval));
+        for usage in std::iter::once(&mut cpu.total).chain(&mut cpu.cores) {
+            usage.level = self.threshold.level(usage.busy());
+        }
+        (cpu, status)
+    }
+}
+
+pub struct CpuInfoCollector {
+    pub threshold: StateThreshold
+}
+
+impl CpuInfoCollector {
+    pub fn new(config: &Config) -> Self { CpuInfoCollector { threshold: config.thresholds.governor.clone() } }
+}
+
+impl Collector for CpuInfoCollector {
+    type Output = CpuInfo;
+
+    fn name(&self) -> &str { "cpu_info" }
+
+    fn section(&self) -> &str { "cpuinfo" }
+
+    fn collect(&self) -> (CpuInfo, SectionStatus) {
+        let (mut info, status) = section(get_cpu_info());
+        info.governor_level = info.governors.iter().map(|governor| self.threshold.level(governor)).max().unwrap_or_default();
+        (info, status)
+    }
+}
+
+pub struct UptimeCollector;
+
+impl Collector for UptimeCollector {
+    type Output = u64;
+
+    fn name(&self) -> &str { "uptime" }
+
+    fn section(&self) -> &str { "uptime" }
+
+    fn collect(&self) -> (u64, SectionStatus) { section(get_uptime()) }
+}
+
+pub struct LastLoginCollector;
+
+impl Collector for LastLoginCollector {
+    type Output = LoginInfo;
+
+    fn name(&self) -> &str { "last_login" }
+
+    fn section(&self) -> &str { "lastlogin" }
+
+    fn collect(&self) -> (LoginInfo, SectionStatus) { section(get_last_login()) }
+}
+
+pub struct DiskCollector {
+    pub disks:     Vec<SysDisk>,
+    pub threshold: Threshold
+}
+
+impl DiskCollector {
+    pub fn new(config: &Config) -> Self { DiskCollector { disks: config.disk.clone(), threshold: config.thresholds.disk } }
+}
+
+impl Collector for DiskCollector {
+    type Output = Vec<DiskInfo>;
+
+    fn name(&self) -> &str { "disks" }
+
+    fn section(&self) -> &str { "disk" }
+
+    fn collect(&self) -> (Vec<DiskInfo>, SectionStatus) { partial(get_disks(&self.disks, &self.threshold)) }
+}
+
+pub struct SystemdCollector {
+    pub services:  Vec<SysService>,
+    pub threshold: StateThreshold
+}
+
+impl SystemdCollector {
+    pub fn new(config: &Config) -> Self {
+        SystemdCollector { services: config.systemctl.clone(), threshold: config.thresholds.service.clone() }
+    }
+}
+
+impl Collector for SystemdCollector {
+    type Output = Vec<Service>;
+
+    fn name(&self) -> &str { "services" }
+
+    fn section(&self) -> &str { "systemctl" }
+
+    fn collect(&self) -> (Vec<Service>, SectionStatus) { partial(get_service_all(&self.services, &self.threshold)) }
+}
+
+pub struct DockerCollector {
+    pub containers: Vec<SysDocker>
+}
+
+impl DockerCollector {
+    pub fn new(config: &Config) -> Self { DockerCollector { containers: config.docker.clone() } }
+}
+
+impl Collector for DockerCollector {
+    type Output = Vec<Docker>;
+
+    fn name(&self) -> &str { "dockers" }
+
+    fn section(&self) -> &str { "docker" }
+
+    fn collect(&self) -> (Vec<Docker>, SectionStatus) { section(get_docker(&self.containers)) }
+}
+
+pub struct LibvirtCollector {
+    pub vms:       Vec<SysVm>,
+    pub threshold: StateThreshold
+}
+
+impl LibvirtCollector {
+    pub fn new(config: &Config) -> Self { LibvirtCollector { vms: config.vm.clone(), threshold: config.thresholds.vm.clone() } }
+}
+
+impl Collector for LibvirtCollector {
+    type Output = Vec<VmInfo>;
+
+    fn name(&self) -> &str { "vms" }
+
+    fn section(&self) -> &str { "vm" }
+
+    fn collect(&self) -> (Vec<VmInfo>, SectionStatus) { partial(get_vms(&self.vms, &self.threshold)) }
+}
+
+pub struct NvidiaCollector {
+    pub gpus:      Vec<SysGpu>,
+    pub threshold: Threshold
+}
+
+impl NvidiaCollector {
+    pub fn new(config: &Config) -> Self { NvidiaCollector { gpus: config.gpu.clone(), threshold: config.thresholds.temp } }
+}
+
+impl Collector for NvidiaCollector {
+    type Output = Vec<GpuInfo>;
+
+    fn name(&self) -> &str { "gpus" }
+
+    fn section(&self) -> &str { "gpu" }
+
+    fn collect(&self) -> (Vec<GpuInfo>, SectionStatus) {
+        let (mut gpus, status) = partial(get_gpus(&self.gpus));
+        for gpu in &mut gpus {
+            gpu.temp_level = self.threshold.level(gpu.temp as f64);
+        }
+        (gpus, status)
+    }
+}
@@ -0,0 +1,232 @@
+// comp := cargo build
+// run  := sudo ../target/debug/dashboard-motd -c /home/jyh/.config/motd.toml dashboard
+// dir  := .
+// kid  :=
+
+use std::io::{self, Write};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::load_config::Config;
+use crate::plain_text::generate_sections;
+use crate::system_stats::SystemStats;
+use crate::utils::visible_width;
+
+// How often keys are polled for while a refresh is running, so its result shows up promptly.
+const PENDING_POLL: Duration = Duration::from_millis(100);
+
+struct Dashboard<'a> {
+    config:    &'a Config,
+    // None until the first collection finishes.
+    stats:     Option<SystemStats>,
+    interval:  Duration,
+    selected:  usize,
+    refreshed: Instant,
+    // Collection runs off the event loop, so keys and resizes are still handled meanwhile.
+    pending:   Option<Receiver<SystemStats>>
+}
+
+fn restore_terminal() {
+    let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+// Puts the terminal back when dropped, also when unwinding from a panic.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) { restore_terminal(); }
+}
+
+fn fit(line: &str, width: usize) -> String {
+    let len = visible_width(line);
+    if len > width {
+        let mut text = String::new();
+        let mut count = 0;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                text.push(c);
+                for c in chars.by_ref() {
+                    text.push(c);
+                    if c == 'm' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            if count + 1 >= width {
+                break;
+            }
+            text.push(c);
+            count += 1;
+        }
+        text + "…\x1b[0m"
+    }
+    else {
+        format!("{}{}", line, " ".repeat(width - len))
+    }
+}
+
+fn panel(title: &str, body: &str, width: usize) -> Vec<String> {
+    let inner = width.saturating_sub(2);
+    let head: String = format!("─ {} ", title).chars().take(inner).collect();
+    let head = format!("{}{}", head, "─".repeat(inner - head.chars().count()));
+    let mut lines = vec![format!("┌{}┐", head)];
+    for line in body.lines() {
+        lines.push(format!("│{}│", fit(line, inner)));
+    }
+    lines.push(format!("└{}┘", "─".repeat(inner)));
+    lines
+}
+
+impl<'a> Dashboard<'a> {
+    fn new(config: &'a Config, interval: Duration) -> Self {
+        let mut dashboard = Dashboard { config, stats: None, interval, selected: 0, refreshed: Instant::now(), pending: None };
+        dashboard.refresh();
+        dashboard
+    }
+
+    fn refresh(&mut self) {
+        if self.pending.is_some() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let config = self.config.clone();
+        thread::spawn(move || {
+            let _ = tx.send(SystemStats::new(&config));
+        });
+        self.pending = Some(rx);
+    }
+
+    // Picks up the stats of a finished refresh.
+    fn receive(&mut self) {
+        match self.pending.as_ref().map(Receiver::try_recv) {
+            Some(Ok(stats)) => {
+                self.stats = Some(stats);
+                self.refreshed = Instant::now();
+                self.pending = None;
+            }
+            // The collecting thread died, try again on the next refresh.
+            Some(Err(TryRecvError::Disconnected)) => {
+                self.pending = None;
+                self.refreshed = Instant::now();
+            }
+            _ => {}
+        }
+    }
+
+    fn sections(&self) -> Vec<(String, String)> {
+        self.stats.as_ref().map(|stats| generate_sections(self.config, stats)).unwrap_or_default()
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let (width, height) = (cols as usize, rows as usize);
+        let sections = self.sections();
+        let selected = self.selected.min(sections.len().saturating_sub(1));
+
+        let mut lines: Vec<(String, bool)> = Vec::new();
+        let mut selected_range = (0, 0);
+        for (i, (title, body)) in sections.iter().enumerate() {
+            let start = lines.len();
+            lines.extend(panel(title, body, width).into_iter().map(|l| (l, i == selected)));
+            if i == selected {
+                selected_range = (start, lines.len());
+            }
+        }
+
+        let view = height.saturating_sub(1);
+        let scroll = if selected_range.1 > view { (selected_range.1 - view).min(selected_range.0) } else { 0 };
+
+        queue!(out, Clear(ClearType::All))?;
+        for (row, (line, highlight)) in lines.iter().skip(scroll).take(view).enumerate() {
+            queue!(out, MoveTo(0, row as u16))?;
+            if *highlight {
+                queue!(out, SetAttribute(Attribute::Bold), Print(line), SetAttribute(Attribute::Reset))?;
+            }
+            else {
+                queue!(out, Print(line))?;
+            }
+        }
+        let updated = match (&self.stats, &self.pending) {
+            (None, _) => "collecting…".to_string(),
+            (Some(_), Some(_)) => format!("updated {}s ago, refreshing…", self.refreshed.elapsed().as_secs()),
+            (Some(_), None) => format!("updated {}s ago", self.refreshed.elapsed().as_secs())
+        };
+        let footer = format!(" refresh every {}s · {} · ↑/↓ select · r refresh · q quit", self.interval.as_secs(), updated);
+        queue!(
+            out,
+            MoveTo(0, rows.saturating_sub(1)),
+            SetAttribute(Attribute::Reverse),
+            Print(fit(&footer, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        out.flush()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.sections().len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.selected = (self.selected + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = count.saturating_sub(1),
+            _ => {}
+        }
+        true
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<()> {
+        loop {
+            self.draw(out)?;
+            let timeout = if self.pending.is_some() {
+                PENDING_POLL
+            }
+            else {
+                self.interval.saturating_sub(self.refreshed.elapsed()).min(Duration::from_secs(1))
+            };
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+            self.receive();
+            if self.pending.is_none() && self.refreshed.elapsed() >= self.interval {
+                self.refresh();
+            }
+        }
+    }
+}
+
+pub fn run(config: &Config, interval: Duration) -> io::Result<()> {
+    let mut dashboard = Dashboard::new(config, interval);
+    let mut out = io::stdout();
+
+    // Restore the terminal before the panic message is printed, or it is lost with the screen.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        hook(info);
+    }));
+
+    terminal::enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    execute!(out, EnterAlternateScreen, Hide)?;
+    dashboard.event_loop(&mut out)
+}
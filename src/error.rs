@@ -0,0 +1,137 @@
+// run  := cargo run --
+// dir  := .
+// kid  :=
+
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum CollectError {
+    Unavailable(String),
+    Failed(String),
+    TimedOut
+}
+
+impl fmt::Display for CollectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectError::Unavailable(msg) => write!(f, "unavailable: {}", msg),
+            CollectError::Failed(msg) => write!(f, "{}", msg),
+            CollectError::TimedOut => write!(f, "timed out")
+        }
+    }
+}
+
+impl From<io::Error> for CollectError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => CollectError::Unavailable(e.to_string()),
+            _ => CollectError::Failed(e.to_string())
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum SectionStatus {
+    #[default]
+    Ok,
+    Unavailable(String),
+    Error(String),
+    TimedOut
+}
+
+impl From<&CollectError> for SectionStatus {
+    fn from(e: &CollectError) -> Self {
+        match e {
+            CollectError::Unavailable(msg) => SectionStatus::Unavailable(msg.clone()),
+            CollectError::Failed(msg) => SectionStatus::Error(msg.clone()),
+            CollectError::TimedOut => SectionStatus::TimedOut
+        }
+    }
+}
+
+impl fmt::Display for SectionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SectionStatus::Ok => write!(f, "ok"),
+            SectionStatus::Unavailable(msg) => write!(f, "unavailable ({})", msg),
+            SectionStatus::Error(msg) => write!(f, "error: {}", msg),
+            SectionStatus::TimedOut => write!(f, "timed out")
+        }
+    }
+}
+
+impl SectionStatus {
+    pub fn is_ok(&self) -> bool { *self == SectionStatus::Ok }
+}
+
+thread_local! {
+    // When the section being collected on this thread times out, set by `collectors::spawn`.
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+pub fn set_deadline(deadline: Instant) { DEADLINE.with(|d| d.set(Some(deadline))); }
+
+pub fn deadline() -> Option<Instant> { DEADLINE.with(|d| d.get()) }
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+// Children get their own process group so `output_until` can kill all of it.
+pub fn own_group(command: &mut Command) -> &mut Command { command.process_group(0) }
+
+// Like `wait_with_output`, but the child is killed and reaped once the deadline passes, so a hung
+// command does not outlive its section. Returns `None` when it had to be killed.
+pub fn output_until(mut child: Child, deadline: Option<Instant>) -> io::Result<Option<Output>> {
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            // The child leads its own process group, so whatever it started goes down with it.
+            unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok(Some(Output { status, stdout: stdout.join().unwrap_or_default(), stderr: stderr.join().unwrap_or_default() }))
+}
+
+pub fn run_command(command: &mut Command) -> Result<String, CollectError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let child = own_group(command).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => CollectError::Unavailable(format!("{} not found", program)),
+        _ => CollectError::Failed(format!("{}: {}", program, e))
+    })?;
+    let output = output_until(child, deadline())
+        .map_err(|e| CollectError::Failed(format!("{}: {}", program, e)))?
+        .ok_or(CollectError::TimedOut)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("exited with an error");
+        if message.starts_with(&program) {
+            return Err(CollectError::Failed(message.to_string()));
+        }
+        return Err(CollectError::Failed(format!("{}: {}", program, message)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
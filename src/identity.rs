@@ -0,0 +1,118 @@
+// run  := cargo run -- -f json
+// dir  := .
+// kid  :=
+
+use std::ffi::CStr;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::collectors::{section, Collector};
+use crate::error::{CollectError, SectionStatus};
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct IdentityInfo {
+    pub hostname:       String,
+    pub os:             String,
+    pub kernel:         String,
+    pub arch:           String,
+    pub virtualization: String
+}
+
+fn field(raw: &[libc::c_char]) -> String { unsafe { CStr::from_ptr(raw.as_ptr()) }.to_string_lossy().to_string() }
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+pub fn get_os_release() -> Option<String> {
+    let content = read_trimmed("/etc/os-release").or_else(|| read_trimmed("/usr/lib/os-release"))?;
+    let value = |key: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(|v| v.trim_matches(|c| c == '"' || c == '\'').to_string())
+            .filter(|v| !v.is_empty())
+    };
+    value("PRETTY_NAME").or_else(|| match (value("NAME"), value("VERSION")) {
+        (Some(name), Some(version)) => Some(format!("{} {}", name, version)),
+        (name, _) => name
+    })
+}
+
+// Container checks come first, since a container on a VM should report the container.
+pub fn detect_virtualization() -> String {
+    if Path::new("/.dockerenv").exists() {
+        return "docker".to_string();
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return "podman".to_string();
+    }
+    if let Ok(environ) = fs::read("/proc/1/environ") {
+        if let Some(kind) = environ.split(|b| *b == 0).find_map(|var| var.strip_prefix(b"container=")) {
+            return String::from_utf8_lossy(kind).to_string();
+        }
+    }
+    if Path::new("/proc/vz").exists() && !Path::new("/proc/bc").exists() {
+        return "openvz".to_string();
+    }
+    if read_trimmed("/proc/sys/kernel/osrelease").is_some_and(|release| release.to_lowercase().contains("microsoft")) {
+        return "wsl".to_string();
+    }
+
+    let dmi = ["sys_vendor", "product_name", "bios_vendor"]
+        .iter()
+        .filter_map(|name| read_trimmed(&format!("/sys/class/dmi/id/{}", name)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let known = [
+        ("KVM", "kvm"),
+        ("QEMU", "qemu"),
+        ("VMware", "vmware"),
+        ("VirtualBox", "oracle"),
+        ("innotek", "oracle"),
+        ("Virtual Machine", "microsoft"),
+        ("Xen", "xen"),
+        ("Amazon EC2", "amazon"),
+        ("Google Compute Engine", "google"),
+        ("Parallels", "parallels"),
+        ("Bochs", "bochs")
+    ];
+    if let Some((_, kind)) = known.iter().find(|(marker, _)| dmi.contains(marker)) {
+        return kind.to_string();
+    }
+    if let Some(kind) = read_trimmed("/sys/hypervisor/type") {
+        return kind;
+    }
+    let hypervisor = fs::read_to_string("/proc/cpuinfo")
+        .map(|cpuinfo| cpuinfo.lines().any(|line| line.starts_with("flags") && line.split_whitespace().any(|flag| flag == "hypervisor")))
+        .unwrap_or(false);
+    if hypervisor { "vm" } else { "none" }.to_string()
+}
+
+pub fn get_identity() -> Result<IdentityInfo, CollectError> {
+    let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut uts) } != 0 {
+        return Err(CollectError::Failed(format!("uname: {}", std::io::Error::last_os_error())));
+    }
+    Ok(IdentityInfo {
+        hostname:       field(&uts.nodename),
+        os:             get_os_release().unwrap_or_else(|| field(&uts.sysname)),
+        kernel:         field(&uts.release),
+        arch:           field(&uts.machine),
+        virtualization: detect_virtualization()
+    })
+}
+
+pub struct IdentityCollector;
+
+impl Collector for IdentityCollector {
+    type Output = IdentityInfo;
+
+    fn name(&self) -> &str { "identity" }
+
+    fn section(&self) -> &str { "header" }
+
+    fn collect(&self) -> (IdentityInfo, SectionStatus) { section(get_identity()) }
+}
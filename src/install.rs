@@ -0,0 +1,141 @@
+// run  := cargo run -- install --root /tmp/motd-root
+// dir  := .
+// kid  :=
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+const MARKER: &str = "# Installed by dashboard-motd";
+const UPDATE_MOTD_DIR: &str = "etc/update-motd.d";
+const UPDATE_MOTD_SCRIPT: &str = "etc/update-motd.d/50-dashboard-motd";
+const PROFILE_SCRIPT: &str = "etc/profile.d/dashboard-motd.sh";
+const SERVICE_UNIT: &str = "etc/systemd/system/dashboard-motd-collect.service";
+const TIMER_UNIT: &str = "etc/systemd/system/dashboard-motd-collect.timer";
+const TIMER_LINK: &str = "etc/systemd/system/timers.target.wants/dashboard-motd-collect.timer";
+
+pub struct InstallOptions {
+    pub root:   PathBuf,
+    pub binary: PathBuf,
+    pub config: Option<PathBuf>,
+    pub hook:   String,
+    pub timer:  bool
+}
+
+// Quotes a path for /bin/sh: single quotes, with embedded ones written as '\''.
+fn shell_quote(path: &Path) -> String { format!("'{}'", path.display().to_string().replace('\'', "'\\''")) }
+
+// Quotes a path for a systemd command line, which has its own rules: double quotes with `\` and
+// `"` escaped, and `%` and `$` doubled so they are not taken as specifiers or variables.
+fn systemd_quote(path: &Path) -> String {
+    let escaped = path.display().to_string().replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn command_line(opts: &InstallOptions, quote: fn(&Path) -> String, args: &str) -> String {
+    match &opts.config {
+        Some(config) => format!("{} -c {} {}", quote(&opts.binary), quote(config), args).trim_end().to_string(),
+        None => format!("{} {}", quote(&opts.binary), args).trim_end().to_string()
+    }
+}
+
+fn write_file(path: &Path, content: &str, mode: u32) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, content)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+fn update_motd_script(opts: &InstallOptions) -> String {
+    format!("#!/bin/sh\n{}; remove with `dashboard-motd uninstall`.\nexec {}\n", MARKER, command_line(opts, shell_quote, "--color always"))
+}
+
+fn profile_script(opts: &InstallOptions) -> String {
+    format!(
+        "{marker}; remove with `dashboard-motd uninstall`.
+case $- in
+    *i*)
+        if [ -z \"$DASHBOARD_MOTD_SHOWN\" ] && [ -x {binary} ]; then
+            {command}
+            export DASHBOARD_MOTD_SHOWN=1
+        fi
+        ;;
+esac
+",
+        marker = MARKER,
+        binary = shell_quote(&opts.binary),
+        command = command_line(opts, shell_quote, "")
+    )
+}
+
+fn service_unit(opts: &InstallOptions) -> String {
+    format!(
+        "{}\n[Unit]\nDescription=Collect dashboard-motd snapshot\n\n[Service]\nType=oneshot\nExecStart={}\n",
+        MARKER,
+        command_line(opts, systemd_quote, "collect")
+    )
+}
+
+fn timer_unit() -> String {
+    format!(
+        "{}\n[Unit]\nDescription=Refresh dashboard-motd snapshot\n\n[Timer]\nOnBootSec=30s\nOnUnitActiveSec=1min\n\n[Install]\nWantedBy=timers.target\n",
+        MARKER
+    )
+}
+
+pub fn install(opts: &InstallOptions) -> io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    let hook = match opts.hook.as_str() {
+        "auto" if opts.root.join(UPDATE_MOTD_DIR).is_dir() => "update-motd",
+        "auto" => "profile",
+        other => other
+    };
+    let (path, content) = match hook {
+        "update-motd" => (opts.root.join(UPDATE_MOTD_SCRIPT), update_motd_script(opts)),
+        "profile" => (opts.root.join(PROFILE_SCRIPT), profile_script(opts)),
+        other => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown hook type: {}", other)))
+    };
+    write_file(&path, &content, 0o755)?;
+    written.push(path);
+
+    if opts.timer {
+        let service = opts.root.join(SERVICE_UNIT);
+        write_file(&service, &service_unit(opts), 0o644)?;
+        written.push(service);
+        let timer = opts.root.join(TIMER_UNIT);
+        write_file(&timer, &timer_unit(), 0o644)?;
+        written.push(timer);
+
+        let link = opts.root.join(TIMER_LINK);
+        if let Some(dir) = link.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if link.symlink_metadata().is_ok() {
+            fs::remove_file(&link)?;
+        }
+        symlink(Path::new("/").join(TIMER_UNIT), &link)?;
+        written.push(link);
+    }
+    Ok(written)
+}
+
+fn installed_by_us(path: &Path) -> bool { fs::read_to_string(path).map(|content| content.contains(MARKER)).unwrap_or(false) }
+
+pub fn uninstall(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    let link = root.join(TIMER_LINK);
+    if link.symlink_metadata().is_ok() {
+        fs::remove_file(&link)?;
+        removed.push(link);
+    }
+    for file in [UPDATE_MOTD_SCRIPT, PROFILE_SCRIPT, SERVICE_UNIT, TIMER_UNIT] {
+        let path = root.join(file);
+        if installed_by_us(&path) {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
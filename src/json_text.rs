@@ -0,0 +1,15 @@
+// comp := cargo build
+// run  := sudo ../target/debug/dashboard-motd -c /home/jyh/.config/motd.toml -f json
+// dir  := .
+// kid  :=
+
+use crate::system_stats::SystemStats;
+
+pub fn generate_json(stats: &SystemStats) -> String {
+    let mut text = serde_json::to_string_pretty(stats).unwrap_or_else(|e| {
+        eprintln!("Failed to serialize system stats: {}", e);
+        std::process::exit(1);
+    });
+    text += "\n";
+    text
+}
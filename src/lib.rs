@@ -0,0 +1,26 @@
+// comp := cargo build
+// dir  := .
+// kid  :=
+
+pub mod banner;
+pub mod cache;
+pub mod collectors;
+pub mod dashboard;
+pub mod error;
+pub mod identity;
+pub mod install;
+pub mod json_text;
+pub mod line_text;
+pub mod load_config;
+pub mod plain_text;
+pub mod plugin;
+pub mod pressure;
+pub mod prometheus;
+pub mod segment;
+pub mod serve;
+pub mod status_bar;
+pub mod system_stats;
+pub mod template;
+pub mod theme;
+pub mod thresholds;
+pub mod utils;
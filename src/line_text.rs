@@ -0,0 +1,215 @@
+// run  := cargo run -- --format influx
+// dir  := .
+// kid  :=
+
+use std::borrow::Cow;
+use std::fmt::Write as FmtWrite;
+
+use serde_json::Value;
+
+use crate::load_config::Config;
+use crate::system_stats::SystemStats;
+
+enum Field {
+    Int(u64),
+    Float(f64)
+}
+
+struct Point<'a> {
+    measurement: &'static str,
+    tags:        Vec<(&'static str, Cow<'a, str>)>,
+    fields:      Vec<(&'static str, Field)>
+}
+
+fn point<'a>(measurement: &'static str, tags: Vec<(&'static str, &'a str)>, fields: Vec<(&'static str, Field)>) -> Point<'a> {
+    Point { measurement, tags: tags.into_iter().map(|(name, value)| (name, Cow::Borrowed(value))).collect(), fields }
+}
+
+fn points<'a>(config: &Config, stats: &'a SystemStats) -> Vec<Point<'a>> {
+    use Field::{Float, Int};
+
+    let mut points = Vec::new();
+    let memory = &stats.memory;
+    if config.memory && stats.collected("memory") {
+        points.push(point(
+            "memory",
+            vec![],
+            vec![
+                ("total_mem", Int(memory.total_mem)),
+                ("free_mem", Int(memory.free_mem)),
+                ("active_mem", Int(memory.active_mem)),
+                ("buffer", Int(memory.buffer)),
+                ("cache", Int(memory.cache)),
+                ("available_mem", Int(memory.available_mem)),
+                ("total_swap", Int(memory.total_swap)),
+                ("free_swap", Int(memory.free_swap)),
+            ]
+        ));
+        let (show, details) = (&config.memory_details, &memory.details);
+        let mut fields = Vec::new();
+        if show.dirty {
+            fields.extend([("dirty", Int(details.dirty)), ("writeback", Int(details.writeback))]);
+        }
+        if show.shmem {
+            fields.push(("shmem", Int(details.shmem)));
+        }
+        if show.slab {
+            fields.extend([("sreclaimable", Int(details.sreclaimable)), ("sunreclaim", Int(details.sunreclaim))]);
+        }
+        if show.hugepages {
+            fields.extend([
+                ("hugepages_total", Int(details.hugepages_total)),
+                ("hugepages_free", Int(details.hugepages_free)),
+                ("hugepage_size", Int(details.hugepage_size)),
+            ]);
+        }
+        if show.commit {
+            fields.extend([("committed", Int(details.committed)), ("commit_limit", Int(details.commit_limit))]);
+        }
+        if !fields.is_empty() {
+            points.push(point("memory_details", vec![], fields));
+        }
+        for zram in &details.zram {
+            points.push(point(
+                "zram",
+                vec![("device", &zram.name)],
+                vec![("orig", Int(zram.orig)), ("compressed", Int(zram.compressed)), ("mem_used", Int(zram.mem_used))]
+            ));
+        }
+        for swap in &details.swaps {
+            points.push(point("swap_device", vec![("device", &swap.name), ("type", &swap.kind)], vec![("size", Int(swap.size)), ("used", Int(swap.used))]));
+        }
+    }
+    if config.cpuload && stats.collected("load_avg") {
+        let load = &stats.load_avg;
+        points.push(point("load", vec![], vec![("one", Float(load.one)), ("five", Float(load.five)), ("fifteen", Float(load.fifteen)), ("cpus", Float(load.cpus))]));
+    }
+    if config.cpu.enabled && stats.collected("cpu") {
+        for usage in std::iter::once(&stats.cpu.total).chain(&stats.cpu.cores) {
+            points.push(point(
+                "cpu",
+                vec![("cpu", &usage.name)],
+                vec![
+                    ("user", Float(usage.user)),
+                    ("system", Float(usage.system)),
+                    ("iowait", Float(usage.iowait)),
+                    ("steal", Float(usage.steal)),
+                    ("idle", Float(usage.idle)),
+                ]
+            ));
+        }
+    }
+    if config.cpuinfo && stats.collected("cpu_info") {
+        let info = &stats.cpu_info;
+        points.push(point(
+            "cpu_info",
+            vec![],
+            vec![
+                ("sockets", Int(info.sockets as u64)),
+                ("cores", Int(info.cores as u64)),
+                ("threads", Int(info.threads as u64)),
+                ("offline", Int(info.offline.len() as u64)),
+            ]
+        ));
+        for freq in &info.freqs {
+            points.push(point("cpu_freq", vec![("cpu", &freq.name)], vec![("cur", Int(freq.cur)), ("min", Int(freq.min)), ("max", Int(freq.max))]));
+        }
+    }
+    for pressure in &stats.pressure {
+        for (kind, avg) in [("some", Some(&pressure.some)), ("full", pressure.full.as_ref())] {
+            if let Some(avg) = avg {
+                points.push(point(
+                    "pressure",
+                    vec![("scope", &pressure.scope), ("resource", &pressure.resource), ("kind", kind)],
+                    vec![("avg10", Float(avg.avg10)), ("avg60", Float(avg.avg60)), ("avg300", Float(avg.avg300))]
+                ));
+            }
+        }
+    }
+    if config.uptime && stats.collected("uptime") {
+        points.push(point("uptime", vec![], vec![("seconds", Int(stats.uptime))]));
+    }
+    for temp in &stats.cpu_temp {
+        points.push(point("cpu_temp", vec![("chip", &temp.chip), ("sensor", &temp.name)], vec![("temp", Float(temp.temp))]));
+    }
+    for disk in &stats.disks {
+        points.push(point("disk", vec![("disk", &disk.name)], vec![("total", Int(disk.total)), ("used", Int(disk.used))]));
+        for subvol in &disk.subvol {
+            points.push(point("subvol", vec![("disk", &disk.name), ("subvol", &subvol.name)], vec![("used", Int(subvol.used))]));
+        }
+    }
+    for service in stats.services.iter().filter(|service| !service.failed) {
+        points.push(point("service", vec![("service", &service.name)], vec![("memory", Int(service.memory))]));
+    }
+    for vm in &stats.vms {
+        points.push(point(
+            "vm",
+            vec![("vm", &vm.name)],
+            vec![("used_mem", Int(vm.used_mem)), ("total_mem", Int(vm.total_mem)), ("cpus", Int(vm.cpus))]
+        ));
+    }
+    for (index, gpu) in stats.gpus.iter().enumerate() {
+        let mut gpu_point = point(
+            "gpu",
+            vec![("sensor", &gpu.temp_name)],
+            vec![("used_vram", Int(gpu.used_vram)), ("total_vram", Int(gpu.total_vram)), ("temp", Int(gpu.temp))]
+        );
+        gpu_point.tags.insert(0, ("gpu", Cow::Owned(gpu.label(index))));
+        points.push(gpu_point);
+    }
+    for plugin in &stats.plugins {
+        for row in &plugin.rows {
+            if let Value::Number(n) = &row.value {
+                if let Some(value) = n.as_f64() {
+                    points.push(point("plugin", vec![("plugin", &plugin.name), ("label", &row.label)], vec![("value", Float(value))]));
+                }
+            }
+        }
+    }
+    points
+}
+
+fn influx_escape(value: &str) -> String { value.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ") }
+
+pub fn generate_influx(config: &Config, stats: &SystemStats) -> String {
+    let mut text = String::new();
+    let timestamp = stats.timestamp as u128 * 1_000_000_000;
+    for point in points(config, stats) {
+        let mut key = point.measurement.to_string();
+        for (name, value) in point.tags.iter().filter(|(_, value)| !value.is_empty()) {
+            write!(key, ",{}={}", name, influx_escape(value)).unwrap();
+        }
+        let fields: Vec<String> = point
+            .fields
+            .iter()
+            .map(|(name, field)| match field {
+                Field::Int(v) => format!("{}={}i", name, v),
+                Field::Float(v) => format!("{}={}", name, v)
+            })
+            .collect();
+        writeln!(text, "motd_{} {} {}", key, fields.join(","), timestamp).unwrap();
+    }
+    text
+}
+
+fn graphite_escape(value: &str) -> String {
+    value.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+pub fn generate_graphite(config: &Config, stats: &SystemStats) -> String {
+    let mut text = String::new();
+    for point in points(config, stats) {
+        let mut path = format!("motd.{}", point.measurement);
+        for (_, value) in point.tags.iter().filter(|(_, value)| !value.is_empty()) {
+            write!(path, ".{}", graphite_escape(value)).unwrap();
+        }
+        for (name, field) in &point.fields {
+            let value = match field {
+                Field::Int(v) => v.to_string(),
+                Field::Float(v) => v.to_string()
+            };
+            writeln!(text, "{}.{} {} {}", path, name, value, stats.timestamp).unwrap();
+        }
+    }
+    text
+}
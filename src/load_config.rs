@@ -4,14 +4,21 @@
 
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+
+use crate::plugin::plugin_name;
 
 fn default_true() -> bool { true }
 
 fn default_none() -> String { "none".to_string() }
 
-#[derive(Deserialize, Debug)]
+fn default_auto() -> String { "auto".to_string() }
+
+fn default_columns() -> usize { 3 }
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct SysService {
     #[serde(default = "default_none")]
     pub name:    String,
@@ -19,7 +26,7 @@ pub struct SysService {
     pub display: String
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct SysDocker {
     #[serde(default = "default_none")]
     pub name:    String,
@@ -27,17 +34,21 @@ pub struct SysDocker {
     pub display: String
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct SysDisk {
     #[serde(default = "default_none")]
-    pub path:    String,
+    pub path:     String,
     #[serde(default = "default_none")]
-    pub display: String,
+    pub display:  String,
+    #[serde(default)]
+    pub subvol:   Vec<String>,
     #[serde(default)]
-    pub subvol:  Vec<String>
+    pub warn:     Option<f64>,
+    #[serde(default)]
+    pub critical: Option<f64>
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct SysVm {
     #[serde(default = "default_none")]
     pub name:    String,
@@ -45,7 +56,7 @@ pub struct SysVm {
     pub display: String
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct SysGpu {
     #[serde(default = "default_none")]
     pub command: String,
@@ -55,11 +66,462 @@ pub struct SysGpu {
     pub tempdisplay: String
 }
 
+#[derive(Deserialize, Clone)]
+pub struct SysPlugin {
+    #[serde(default = "default_none")]
+    pub name:    String,
+    #[serde(default = "default_none")]
+    pub command: String,
+    #[serde(default)]
+    pub args:    Vec<String>,
+    #[serde(default = "default_none")]
+    pub display: String,
+    #[serde(default)]
+    pub timeout: Option<f64>,
+    #[serde(default)]
+    pub options: toml::Table
+}
+
+#[derive(Clone, Copy)]
+pub struct Threshold {
+    pub warn:     f64,
+    pub critical: f64
+}
+
+// A [thresholds] entry may set only one of its limits, the other keeps the built-in default.
 #[derive(Deserialize)]
+struct PartialThreshold {
+    #[serde(default)]
+    warn:     Option<f64>,
+    #[serde(default)]
+    critical: Option<f64>
+}
+
+fn merge_threshold<'de, D: Deserializer<'de>>(deserializer: D, default: Threshold) -> Result<Threshold, D::Error> {
+    let partial = PartialThreshold::deserialize(deserializer)?;
+    Ok(Threshold { warn: partial.warn.unwrap_or(default.warn), critical: partial.critical.unwrap_or(default.critical) })
+}
+
+#[derive(Deserialize, Clone)]
+pub struct StateThreshold {
+    #[serde(default)]
+    pub warn:     Vec<String>,
+    #[serde(default)]
+    pub critical: Vec<String>
+}
+
+fn default_memory_threshold() -> Threshold { Threshold { warn: 80.0, critical: 95.0 } }
+
+fn memory_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_memory_threshold()) }
+
+fn default_swap_threshold() -> Threshold { Threshold { warn: 50.0, critical: 80.0 } }
+
+fn swap_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_swap_threshold()) }
+
+fn default_disk_threshold() -> Threshold { Threshold { warn: 80.0, critical: 95.0 } }
+
+fn disk_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_disk_threshold()) }
+
+fn default_temp_threshold() -> Threshold { Threshold { warn: 70.0, critical: 85.0 } }
+
+fn temp_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_temp_threshold()) }
+
+fn default_load_threshold() -> Threshold { Threshold { warn: 1.0, critical: 2.0 } }
+
+fn load_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_load_threshold()) }
+
+fn default_cpu_threshold() -> Threshold { Threshold { warn: 80.0, critical: 95.0 } }
+
+fn cpu_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_cpu_threshold()) }
+
+fn default_psi_threshold() -> Threshold { Threshold { warn: 10.0, critical: 30.0 } }
+
+fn psi_threshold<'de, D: Deserializer<'de>>(d: D) -> Result<Threshold, D::Error> { merge_threshold(d, default_psi_threshold()) }
+
+fn default_vm_threshold() -> StateThreshold {
+    StateThreshold {
+        warn:     vec!["paused".to_string(), "pmsuspended".to_string(), "in shutdown".to_string(), "shut off".to_string()],
+        critical: vec!["crashed".to_string()]
+    }
+}
+
+fn default_service_threshold() -> StateThreshold {
+    StateThreshold {
+        warn:     vec!["activating".to_string(), "deactivating".to_string(), "reloading".to_string(), "inactive".to_string()],
+        critical: vec!["failed".to_string()]
+    }
+}
+
+fn default_governor_threshold() -> StateThreshold { StateThreshold { warn: vec!["powersave".to_string()], critical: vec![] } }
+
+#[derive(Deserialize, Clone)]
+pub struct Thresholds {
+    #[serde(default = "default_memory_threshold", deserialize_with = "memory_threshold")]
+    pub memory:   Threshold,
+    #[serde(default = "default_swap_threshold", deserialize_with = "swap_threshold")]
+    pub swap:     Threshold,
+    #[serde(default = "default_disk_threshold", deserialize_with = "disk_threshold")]
+    pub disk:     Threshold,
+    #[serde(default = "default_temp_threshold", deserialize_with = "temp_threshold")]
+    pub temp:     Threshold,
+    #[serde(default = "default_load_threshold", deserialize_with = "load_threshold")]
+    pub load:     Threshold,
+    #[serde(default = "default_cpu_threshold", deserialize_with = "cpu_threshold")]
+    pub cpu:      Threshold,
+    #[serde(default = "default_psi_threshold", deserialize_with = "psi_threshold")]
+    pub psi:      Threshold,
+    #[serde(default = "default_vm_threshold")]
+    pub vm:       StateThreshold,
+    #[serde(default = "default_service_threshold")]
+    pub service:  StateThreshold,
+    #[serde(default = "default_governor_threshold")]
+    pub governor: StateThreshold
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            memory:   default_memory_threshold(),
+            swap:     default_swap_threshold(),
+            disk:     default_disk_threshold(),
+            temp:     default_temp_threshold(),
+            load:     default_load_threshold(),
+            cpu:      default_cpu_threshold(),
+            psi:      default_psi_threshold(),
+            vm:       default_vm_threshold(),
+            service:  default_service_threshold(),
+            governor: default_governor_threshold()
+        }
+    }
+}
+
+const BAR_STYLES: [&str; 3] = ["auto", "unicode", "ascii"];
+
+#[derive(Deserialize, Clone)]
+pub struct Bars {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_auto")]
+    pub style:   String
+}
+
+impl Default for Bars {
+    fn default() -> Self { Bars { enabled: false, style: default_auto() } }
+}
+
+fn default_global_timeout() -> f64 { 5.0 }
+
+// Longest timeout accepted, far above anything a login can wait for but small enough to add to
+// an `Instant` and turn into a `Duration`.
+const MAX_TIMEOUT: f64 = 86400.0;
+
+#[derive(Deserialize, Clone)]
+pub struct Timeouts {
+    #[serde(default = "default_global_timeout")]
+    pub global:    f64,
+    #[serde(default)]
+    pub lastlogin: Option<f64>,
+    #[serde(default)]
+    pub cputemp:   Option<f64>,
+    #[serde(default)]
+    pub cpu:       Option<f64>,
+    #[serde(default)]
+    pub psi:       Option<f64>,
+    #[serde(default)]
+    pub disk:      Option<f64>,
+    #[serde(default)]
+    pub systemctl: Option<f64>,
+    #[serde(default)]
+    pub docker:    Option<f64>,
+    #[serde(default)]
+    pub vm:        Option<f64>,
+    #[serde(default)]
+    pub gpu:       Option<f64>
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            global:    default_global_timeout(),
+            lastlogin: None,
+            cputemp:   None,
+            cpu:       None,
+            psi:       None,
+            disk:      None,
+            systemctl: None,
+            docker:    None,
+            vm:        None,
+            gpu:       None
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn section(&self, timeout: Option<f64>) -> Duration {
+        Duration::from_secs_f64(timeout.unwrap_or(self.global).min(self.global).max(0.0))
+    }
+
+    fn all(&self) -> [(&str, Option<f64>); 10] {
+        [
+            ("global", Some(self.global)),
+            ("lastlogin", self.lastlogin),
+            ("cputemp", self.cputemp),
+            ("cpu", self.cpu),
+            ("psi", self.psi),
+            ("disk", self.disk),
+            ("systemctl", self.systemctl),
+            ("docker", self.docker),
+            ("vm", self.vm),
+            ("gpu", self.gpu)
+        ]
+    }
+
+    pub fn for_section(&self, section: &str) -> Duration {
+        self.section(match section {
+            "lastlogin" => self.lastlogin,
+            "cputemp" => self.cputemp,
+            "cpu" => self.cpu,
+            "psi" => self.psi,
+            "disk" => self.disk,
+            "systemctl" => self.systemctl,
+            "docker" => self.docker,
+            "vm" => self.vm,
+            "gpu" => self.gpu,
+            _ => None
+        })
+    }
+}
+
+fn default_cache_path() -> String { "/run/dashboard-motd/stats.json".to_string() }
+
+fn default_cache_max_age() -> u64 { 300 }
+
+#[derive(Deserialize, Clone)]
+pub struct Cache {
+    #[serde(default = "default_cache_path")]
+    pub path:    String,
+    #[serde(default = "default_cache_max_age")]
+    pub max_age: u64
+}
+
+impl Default for Cache {
+    fn default() -> Self { Cache { path: default_cache_path(), max_age: default_cache_max_age() } }
+}
+
+fn default_listen() -> String { "127.0.0.1:9101".to_string() }
+
+#[derive(Deserialize, Clone)]
+pub struct Serve {
+    #[serde(default = "default_listen")]
+    pub listen: String
+}
+
+impl Default for Serve {
+    fn default() -> Self { Serve { listen: default_listen() } }
+}
+
+fn default_statusbar_fields() -> Vec<String> { vec!["memory".to_string(), "load".to_string(), "disk".to_string()] }
+
+fn default_separator() -> String { " | ".to_string() }
+
+fn default_statusbar_interval() -> u64 { 5 }
+
+fn default_warn_color() -> String { "#ffcc00".to_string() }
+
+fn default_critical_color() -> String { "#ff5555".to_string() }
+
+#[derive(Deserialize, Clone)]
+pub struct StatusBar {
+    #[serde(default = "default_statusbar_fields")]
+    pub fields:         Vec<String>,
+    #[serde(default = "default_separator")]
+    pub separator:      String,
+    #[serde(default = "default_statusbar_interval")]
+    pub interval:       u64,
+    #[serde(default = "default_warn_color")]
+    pub warn_color:     String,
+    #[serde(default = "default_critical_color")]
+    pub critical_color: String
+}
+
+impl Default for StatusBar {
+    fn default() -> Self {
+        StatusBar {
+            fields:         default_statusbar_fields(),
+            separator:      default_separator(),
+            interval:       default_statusbar_interval(),
+            warn_color:     default_warn_color(),
+            critical_color: default_critical_color()
+        }
+    }
+}
+
+fn default_segment_template() -> String { "{load} {mem} {disk}".to_string() }
+
+fn default_segment_color() -> String { "none".to_string() }
+
+// Also the values of `segment --color`.
+pub const SEGMENT_COLORS: [&str; 5] = ["none", "ansi", "tmux", "bash", "zsh"];
+
+#[derive(Deserialize, Clone)]
+pub struct Segment {
+    #[serde(default = "default_segment_template")]
+    pub template: String,
+    #[serde(default = "default_segment_color")]
+    pub color:    String
+}
+
+impl Default for Segment {
+    fn default() -> Self { Segment { template: default_segment_template(), color: default_segment_color() } }
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct ThemeStyles {
+    pub label:     Option<String>,
+    pub value:     Option<String>,
+    pub ok:        Option<String>,
+    pub warn:      Option<String>,
+    pub critical:  Option<String>,
+    pub heading:   Option<String>,
+    pub bar:       Option<String>,
+    pub bar_empty: Option<String>
+}
+
+fn default_theme_name() -> String { "default".to_string() }
+
+#[derive(Deserialize, Clone)]
+pub struct ThemeConfig {
+    #[serde(default = "default_theme_name")]
+    pub name:   String,
+    #[serde(default = "default_auto")]
+    pub color:  String,
+    #[serde(flatten)]
+    pub styles: ThemeStyles
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self { ThemeConfig { name: default_theme_name(), color: default_auto(), styles: ThemeStyles::default() } }
+}
+
+fn default_cpu_interval() -> u64 { 250 }
+
+fn default_per_core() -> String { "strip".to_string() }
+
+const PER_CORE_VIEWS: [&str; 3] = ["strip", "rows", "none"];
+
+#[derive(Deserialize, Clone)]
+pub struct Cpu {
+    #[serde(default)]
+    pub enabled:  bool,
+    // Milliseconds between the two /proc/stat samples.
+    #[serde(default = "default_cpu_interval")]
+    pub interval: u64,
+    // none, strip (one heat cell per core) or rows (one row per core).
+    #[serde(default = "default_per_core")]
+    pub per_core: String
+}
+
+impl Default for Cpu {
+    fn default() -> Self { Cpu { enabled: false, interval: default_cpu_interval(), per_core: default_per_core() } }
+}
+
+// Extra rows for the memory section, all off by default.
+#[derive(Deserialize, Default, Clone, Copy)]
+pub struct MemoryDetails {
+    #[serde(default)]
+    pub dirty:        bool,
+    #[serde(default)]
+    pub shmem:        bool,
+    #[serde(default)]
+    pub slab:         bool,
+    #[serde(default)]
+    pub hugepages:    bool,
+    #[serde(default)]
+    pub commit:       bool,
+    #[serde(default)]
+    pub zram:         bool,
+    #[serde(default)]
+    pub swap_devices: bool
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Psi {
+    #[serde(default)]
+    pub enabled:  bool,
+    // Also show the pressure of each [[systemctl]] service's cgroup.
+    #[serde(default = "default_true")]
+    pub services: bool
+}
+
+impl Default for Psi {
+    fn default() -> Self { Psi { enabled: false, services: true } }
+}
+
+fn default_font() -> String { "small".to_string() }
+
+#[derive(Deserialize, Clone)]
+pub struct Header {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub banner:  bool,
+    #[serde(default = "default_font")]
+    pub font:    String
+}
+
+impl Default for Header {
+    fn default() -> Self { Header { enabled: false, banner: true, font: default_font() } }
+}
+
+// Sections a layout can name, in the built-in order. "plugin" stands for every plugin, a single
+// one is named "plugin.<name>".
+pub const SECTION_ORDER: [&str; 15] =
+    ["header", "memory", "cpuload", "psi", "cpu", "cpuinfo", "cputemp", "uptime", "lastlogin", "disk", "systemctl", "docker", "vm", "plugin", "snapshot"];
+
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum LayoutSection {
+    Name(String),
+    Options {
+        name:       String,
+        #[serde(default = "default_true")]
+        hide_empty: bool
+    }
+}
+
+impl LayoutSection {
+    pub fn name(&self) -> &str {
+        match self {
+            LayoutSection::Name(name) | LayoutSection::Options { name, .. } => name
+        }
+    }
+
+    pub fn hide_empty(&self) -> bool {
+        match self {
+            LayoutSection::Name(_) => true,
+            LayoutSection::Options { hide_empty, .. } => *hide_empty
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct LayoutGroup {
+    #[serde(default = "default_none")]
+    pub heading:  String,
+    #[serde(default)]
+    pub sections: Vec<LayoutSection>,
+    #[serde(default)]
+    pub columns:  bool
+}
+
+#[derive(Deserialize, Clone)]
 pub struct Config {
     #[serde(default = "default_true")]
     pub memory: bool,
 
+    #[serde(default)]
+    pub memory_details: MemoryDetails,
+
     #[serde(default = "default_true")]
     pub uptime: bool,
 
@@ -72,6 +534,22 @@ pub struct Config {
     #[serde(default = "default_none")]
     pub cputemp: String,
 
+    #[serde(default)]
+    pub cpu: Cpu,
+
+    #[serde(default)]
+    pub cpuinfo: bool,
+
+    #[serde(default)]
+    pub psi: Psi,
+
+    #[serde(default = "default_none")]
+    pub template: String,
+
+    // Most columns sections flow into when the terminal is wide enough, 1 keeps them stacked.
+    #[serde(default = "default_columns")]
+    pub columns: usize,
+
     #[serde(default)]
     pub disk: Vec<SysDisk>,
 
@@ -85,31 +563,126 @@ pub struct Config {
     pub vm: Vec<SysVm>,
 
     #[serde(default)]
-    pub gpu: Vec<SysGpu>
+    pub gpu: Vec<SysGpu>,
+
+    #[serde(default)]
+    pub plugin: Vec<SysPlugin>,
+
+    #[serde(default)]
+    pub thresholds: Thresholds,
+
+    #[serde(default)]
+    pub bars: Bars,
+
+    #[serde(default)]
+    pub timeouts: Timeouts,
+
+    #[serde(default)]
+    pub cache: Cache,
+
+    #[serde(default)]
+    pub serve: Serve,
+
+    #[serde(default)]
+    pub statusbar: StatusBar,
+
+    #[serde(default)]
+    pub segment: Segment,
+
+    #[serde(default)]
+    pub layout: Vec<LayoutGroup>,
+
+    #[serde(default)]
+    pub header: Header,
+
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    // Config file path and a hash of its content, stored in snapshots so a render only uses one
+    // collected with the same config.
+    #[serde(skip)]
+    pub identity: String
+}
+
+fn shown<'a>(display: &'a str, name: &'a str) -> &'a str { if display != "none" { display } else { name } }
+
+// Exporters label series by the shown name, and Prometheus rejects a scrape with two equal series.
+fn unique<'a>(kind: &str, field: &str, names: impl IntoIterator<Item = &'a str>) -> Result<(), String> {
+    let mut seen = Vec::new();
+    for name in names {
+        if seen.contains(&name) {
+            return Err(format!("Duplicate {} name \"{}\", give each a distinct `{}`", kind, name, field));
+        }
+        seen.push(name);
+    }
+    Ok(())
 }
 
+fn one_of(key: &str, value: &str, valid: &[&str]) -> Result<(), String> {
+    if valid.contains(&value) {
+        return Ok(());
+    }
+    Err(format!("Unknown {} \"{}\", valid values are: {}", key, value, valid.join(", ")))
+}
+
+// Checks the config beyond what its types enforce, so mistakes fail at load rather than
+// silently changing the output.
+fn validate(config: &Config) -> Result<(), String> {
+    let valid: Vec<String> =
+        SECTION_ORDER.iter().map(|name| name.to_string()).chain(config.plugin.iter().map(|plugin| format!("plugin.{}", plugin_name(plugin)))).collect();
+    for group in &config.layout {
+        if let Some(unknown) = group.sections.iter().map(LayoutSection::name).find(|name| !valid.iter().any(|valid| valid == name)) {
+            return Err(format!("Unknown layout section \"{}\", valid sections are: {}", unknown, valid.join(", ")));
+        }
+    }
+    // i3bar output redraws every interval seconds, 0 would spin without pausing.
+    if config.statusbar.interval == 0 {
+        return Err("statusbar.interval must be at least 1 second".to_string());
+    }
+    if config.columns < 1 {
+        return Err("columns must be at least 1".to_string());
+    }
+    one_of("cpu.per_core", &config.cpu.per_core, &PER_CORE_VIEWS)?;
+    one_of("bars.style", &config.bars.style, &BAR_STYLES)?;
+    one_of("segment.color", &config.segment.color, &SEGMENT_COLORS)?;
+    let plugin_timeouts = config.plugin.iter().map(|plugin| (plugin.command.as_str(), plugin.timeout));
+    for (name, timeout) in config.timeouts.all().into_iter().chain(plugin_timeouts) {
+        if let Some(timeout) = timeout.filter(|timeout| !(0.0..=MAX_TIMEOUT).contains(timeout)) {
+            return Err(format!("Timeout {} for {} must be between 0 and {} seconds", timeout, name, MAX_TIMEOUT));
+        }
+    }
+    unique("disk", "display", config.disk.iter().map(|disk| shown(&disk.display, &disk.path)))?;
+    unique("service", "display", config.systemctl.iter().map(|service| shown(&service.display, &service.name)))?;
+    unique("docker", "display", config.docker.iter().map(|docker| shown(&docker.display, &docker.name)))?;
+    unique("vm", "display", config.vm.iter().map(|vm| shown(&vm.display, &vm.name)))?;
+    // Plugins are told apart by name, in the status map and as `plugin.<name>` in layouts.
+    let plugin_names: Vec<String> = config.plugin.iter().map(plugin_name).collect();
+    unique("plugin", "name", plugin_names.iter().map(String::as_str))?;
+    Ok(())
+}
+
+// FNV-1a, which unlike `DefaultHasher` gives the same hash in every build.
+fn fnv1a(bytes: &[u8]) -> u64 { bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3)) }
+
 pub struct LoadConfig {
     config: Config
 }
 
 impl LoadConfig {
-    pub fn new(config_path: Option<PathBuf>) -> Self {
-        let content = if let Some(path) = config_path {
+    pub fn new(config_path: Option<PathBuf>) -> Result<Self, String> {
+        let (path, content) = if let Some(path) = config_path {
             if path.exists() {
-                fs::read_to_string(&path).unwrap_or_else(|e| {
-                    eprintln!("Failed to read config file: {}", e);
-                    std::process::exit(1);
-                })
+                let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read config file: {}", e))?;
+                (path, content)
             }
             else {
-                eprintln!("Provided config file does not exist: {:?}", path);
-                std::process::exit(1);
+                return Err(format!("Provided config file does not exist: {:?}", path));
             }
         }
         else {
             let default_path = dirs::config_dir().unwrap_or_else(|| PathBuf::from(".")).join("motd.toml");
             // println!("Using default config path: {:?}", default_path);
-            if default_path.exists() {
+            let content = if default_path.exists() {
                 fs::read_to_string(&default_path).unwrap_or_else(|e| {
                     eprintln!("Failed to read default config file: {}", e);
                     String::new()
@@ -117,15 +690,16 @@ impl LoadConfig {
             }
             else {
                 String::new()
-            }
+            };
+            (default_path, content)
         };
 
-        let config: Config = toml::from_str(&content).unwrap_or_else(|e| {
-            eprintln!("Failed to parse config file: {}", e);
-            std::process::exit(1);
-        });
+        let mut config: Config = toml::from_str(&content).map_err(|e| format!("Failed to parse config file: {}", e))?;
+        validate(&config)?;
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        config.identity = format!("{}#{:016x}", path.display(), fnv1a(content.as_bytes()));
 
-        LoadConfig { config }
+        Ok(LoadConfig { config })
     }
 
     pub fn get_config(&self) -> &Config { &self.config }
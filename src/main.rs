@@ -3,15 +3,16 @@
 // dir  := .
 // kid  :=
 
-mod load_config;
-mod plain_text;
-mod system_stats;
-mod utils;
-
 use clap::{Arg, ArgAction, Command};
-use load_config::LoadConfig;
-use std::path::PathBuf;
-use system_stats::SystemStats;
+use dashboard_motd::install::{self, InstallOptions};
+use dashboard_motd::load_config::{LoadConfig, SEGMENT_COLORS};
+use dashboard_motd::system_stats::SystemStats;
+use dashboard_motd::utils::write_atomic;
+use dashboard_motd::{cache, dashboard, json_text, line_text, plain_text, prometheus, segment, serve, status_bar, template, theme};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 
 fn main() {
     let matches = Command::new("System Info")
@@ -19,9 +20,30 @@ fn main() {
             Arg::new("text")
                 .short('t')
                 .long("text")
-                .help("Display system information as plain text")
+                .help("Display system information as plain text (same as --format text)")
                 .required(false)
-                .action(ArgAction::SetFalse),
+                .conflicts_with("format")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format")
+                .value_parser(["text", "json", "prometheus-textfile", "influx", "graphite", "waybar", "polybar", "i3bar"])
+                .default_value("text")
+                .num_args(1)
+                .required(false),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Write the output atomically to FILE instead of stdout, e.g. a node_exporter textfile .prom file")
+                .num_args(1)
+                .required(false),
         )
         .arg(
             Arg::new("config")
@@ -32,17 +54,281 @@ fn main() {
                 .num_args(1)
                 .required(false),
         )
+        .arg(
+            Arg::new("width")
+                .short('w')
+                .long("width")
+                .value_name("COLUMNS")
+                .help("Lay out text for a terminal this wide instead of detecting it")
+                .value_parser(clap::value_parser!(u16).range(20..))
+                .num_args(1)
+                .required(false),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Colour text output: auto, always, never, or force a depth of 16, 256 or truecolor")
+                .value_parser(["auto", "always", "never", "16", "256", "truecolor"])
+                .num_args(1)
+                .required(false),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Always collect live instead of reading the snapshot cache")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Exit with an error if any section fails to collect")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("dashboard").about("Open a full-screen dashboard with live refresh").arg(
+                Arg::new("interval")
+                    .short('i')
+                    .long("interval")
+                    .value_name("SECONDS")
+                    .help("Refresh interval in seconds")
+                    .value_parser(clap::value_parser!(u64).range(1..))
+                    .default_value("2")
+                    .num_args(1),
+            ),
+        )
+        .subcommand(
+            Command::new("collect")
+                .about("Collect system information into the snapshot cache")
+                .arg(
+                    Arg::new("daemon")
+                        .short('d')
+                        .long("daemon")
+                        .help("Keep running and refresh the cache periodically")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("interval")
+                        .short('i')
+                        .long("interval")
+                        .value_name("SECONDS")
+                        .help("Refresh interval in seconds when running as a daemon")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("60")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("segment")
+                .about("Print a one-line summary for tmux status-right or a shell prompt")
+                .arg(
+                    Arg::new("template")
+                        .long("template")
+                        .value_name("TEMPLATE")
+                        .help("Segment template, e.g. '{load} {mem} {disk:/} {failed}' (defaults to [segment] template)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("color")
+                        .long("color")
+                        .value_name("STYLE")
+                        .help("How to colour warn and critical values (defaults to [segment] color)")
+                        .value_parser(SEGMENT_COLORS)
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("serve").about("Serve metrics in Prometheus text format on /metrics").arg(
+                Arg::new("listen")
+                    .short('l')
+                    .long("listen")
+                    .value_name("ADDR")
+                    .help("Address to listen on (defaults to [serve] listen in the config, 127.0.0.1:9101)")
+                    .num_args(1),
+            ),
+        )
+        .subcommand(
+            Command::new("install")
+                .about("Hook dashboard-motd into login and install the cache timer")
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .visible_alias("prefix")
+                        .value_name("DIR")
+                        .help("Install into DIR instead of /")
+                        .default_value("/")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("hook")
+                        .long("hook")
+                        .value_name("TYPE")
+                        .help("Login hook to install")
+                        .value_parser(["auto", "update-motd", "profile"])
+                        .default_value("auto")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("binary")
+                        .long("binary")
+                        .value_name("PATH")
+                        .help("Path of the dashboard-motd binary used by the hooks (defaults to this executable)")
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("no-timer")
+                        .long("no-timer")
+                        .help("Do not install the systemd timer that refreshes the cache")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("uninstall").about("Remove files written by install").arg(
+                Arg::new("root")
+                    .long("root")
+                    .visible_alias("prefix")
+                    .value_name("DIR")
+                    .help("Uninstall from DIR instead of /")
+                    .default_value("/")
+                    .num_args(1),
+            ),
+        )
         .get_matches();
 
     let config_path = matches.get_one::<String>("config").map(PathBuf::from);
-    let plain_text_flag = matches.get_flag("text");
+    if let Some(width) = matches.get_one::<u16>("width") {
+        // term_width() honours COLUMNS, so the flag only has to override it.
+        env::set_var("COLUMNS", width.to_string());
+    }
+    let format = if matches.get_flag("text") { "text" } else { matches.get_one::<String>("format").unwrap().as_str() };
+
+    if let Some(install_matches) = matches.subcommand_matches("install") {
+        let binary = install_matches.get_one::<String>("binary").map(PathBuf::from).or_else(|| env::current_exe().ok());
+        let opts = InstallOptions {
+            root:   PathBuf::from(install_matches.get_one::<String>("root").unwrap()),
+            binary: binary.unwrap_or_else(|| PathBuf::from("/usr/local/bin/dashboard-motd")),
+            config: config_path.map(|path| path.canonicalize().unwrap_or(path)),
+            hook:   install_matches.get_one::<String>("hook").unwrap().to_string(),
+            timer:  !install_matches.get_flag("no-timer")
+        };
+        match install::install(&opts) {
+            Ok(written) => written.iter().for_each(|path| println!("Installed {}", path.display())),
+            Err(e) => {
+                eprintln!("Install failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        if opts.timer {
+            println!("Run `systemctl daemon-reload && systemctl start dashboard-motd-collect.timer` to start the cache timer.");
+        }
+        return;
+    }
+
+    if let Some(uninstall_matches) = matches.subcommand_matches("uninstall") {
+        let root = PathBuf::from(uninstall_matches.get_one::<String>("root").unwrap());
+        match install::uninstall(&root) {
+            Ok(removed) => removed.iter().for_each(|path| println!("Removed {}", path.display())),
+            Err(e) => {
+                eprintln!("Uninstall failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let config_loader = LoadConfig::new(config_path);
+    let config_loader = LoadConfig::new(config_path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
     let config = config_loader.get_config();
-    let system_stats = SystemStats::new(config);
-    if plain_text_flag {
-        let s = plain_text::generate_text(config, &system_stats);
-        print!("{}", s);
+    let theme = theme::Theme::load(&config.theme, matches.get_one::<String>("color").map(String::as_str)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    theme::init(theme);
+
+    if let Some(dashboard_matches) = matches.subcommand_matches("dashboard") {
+        let interval = Duration::from_secs(*dashboard_matches.get_one::<u64>("interval").unwrap());
+        dashboard::run(config, interval).unwrap_or_else(|e| {
+            eprintln!("Dashboard error: {}", e);
+            std::process::exit(1);
+        });
+        return;
+    }
+
+    if let Some(segment_matches) = matches.subcommand_matches("segment") {
+        let template = segment_matches.get_one::<String>("template").unwrap_or(&config.segment.template);
+        let color = segment_matches.get_one::<String>("color").unwrap_or(&config.segment.color);
+        let system_stats = segment::collect(config, template);
+        print!("{}", segment::generate_segment(config, &system_stats, template, color));
+        return;
+    }
+
+    if let Some(serve_matches) = matches.subcommand_matches("serve") {
+        let listen = serve_matches.get_one::<String>("listen").unwrap_or(&config.serve.listen);
+        serve::run(config, listen).unwrap_or_else(|e| {
+            eprintln!("Failed to serve metrics on {}: {}", listen, e);
+            std::process::exit(1);
+        });
+        return;
+    }
+
+    if let Some(collect_matches) = matches.subcommand_matches("collect") {
+        let interval = Duration::from_secs(*collect_matches.get_one::<u64>("interval").unwrap());
+        loop {
+            let system_stats = SystemStats::new(config);
+            if let Err(e) = cache::write_cache(&config.cache.path, &system_stats) {
+                eprintln!("Failed to write cache file {}: {}", config.cache.path, e);
+                std::process::exit(1);
+            }
+            if !collect_matches.get_flag("daemon") {
+                return;
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    let live = matches.get_flag("no-cache") || matches.get_flag("strict");
+    if format == "i3bar" {
+        status_bar::run_i3bar(config, live).unwrap_or_else(|e| {
+            eprintln!("i3bar output failed: {}", e);
+            std::process::exit(1);
+        });
+        return;
+    }
+    let system_stats = if live { None } else { cache::read_cache(config) }
+        .unwrap_or_else(|| SystemStats::new(config));
+    if matches.get_flag("strict") {
+        let failed: Vec<_> = system_stats.status.iter().filter(|(_, status)| !status.is_ok()).collect();
+        for (section, status) in &failed {
+            eprintln!("{}: {}", section, status);
+        }
+        if !failed.is_empty() {
+            std::process::exit(1);
+        }
+    }
+    let s = match format {
+        "json" => json_text::generate_json(&system_stats),
+        "prometheus-textfile" => prometheus::generate_metrics(config, &system_stats),
+        "influx" => line_text::generate_influx(config, &system_stats),
+        "graphite" => line_text::generate_graphite(config, &system_stats),
+        "waybar" => status_bar::generate_waybar(config, &system_stats),
+        "polybar" => status_bar::generate_polybar(config, &system_stats),
+        // A broken template should not leave the login without a MOTD.
+        _ if config.template != "none" => template::generate_template(config, &system_stats).unwrap_or_else(|e| {
+            eprintln!("{}, falling back to the built-in layout", e);
+            plain_text::generate_text(config, &system_stats)
+        }),
+        _ => plain_text::generate_text(config, &system_stats)
+    };
+    match matches.get_one::<String>("output") {
+        Some(output) => write_atomic(Path::new(output), &s).unwrap_or_else(|e| {
+            eprintln!("Failed to write {}: {}", output, e);
+            std::process::exit(1);
+        }),
+        None => print!("{}", s)
     }
 }
 
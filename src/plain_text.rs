@@ -3,11 +3,30 @@
 // dir  := .
 // kid  :=
 
-use crate::load_config::Config;
-use crate::system_stats::SystemStats;
-use crate::utils::{byte2str, s2time};
+use crate::banner;
+use crate::cache::snapshot_age;
+use crate::error::SectionStatus;
+use crate::load_config::{Config, LayoutGroup, LayoutSection, SECTION_ORDER};
+use crate::plugin::PluginSection;
+use crate::pressure::PressureAvg;
+use crate::system_stats::{CpuUsage, SystemStats};
+use crate::theme;
+use crate::thresholds::{percent, Level};
+use crate::utils::{byte2str, ellipsize, s2time, term_width, utf8_locale, visible_width};
 
-pub fn to_bold(input: &str) -> String { format!("\x1b[1m{}\x1b[0m", input) }
+pub fn to_bold(input: &str) -> String { theme::current().bold(input) }
+
+pub fn to_heading(input: &str) -> String { theme::current().heading(input) }
+
+pub fn to_level(input: &str, level: Level) -> String { theme::current().level(input, level) }
+
+fn marker(level: Level) -> &'static str {
+    match level {
+        Level::Ok => "",
+        Level::Warn => "*",
+        Level::Critical => "!"
+    }
+}
 
 use std::fmt::Write as FmtWrite;
 
@@ -15,84 +34,394 @@ const PADDING_BEFORE: usize = 2;
 const PADDING_AFTER: usize = 15;
 const PADDING_INDENT: usize = 3;
 const PADDING_MEMORY: usize = 12;
+const PADDING_VALUE: usize = 24;
+const BAR_MAX: usize = 40;
+const BAR_EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
 
-pub fn generate_text(config: &Config, stats: &SystemStats) -> String {
+fn bar(config: &Config, pad: usize, pct: f64, level: Level) -> String {
+    let pct_text = format!("{:>6.1}%", pct);
+    let width = term_width().saturating_sub(PADDING_BEFORE + pad + PADDING_VALUE + pct_text.len() + 2).min(BAR_MAX);
+    if width < 5 {
+        return to_level(&pct_text, level);
+    }
+    let fill = pct.clamp(0.0, 100.0) / 100.0;
+    let unicode = match config.bars.style.as_str() {
+        "unicode" => true,
+        "ascii" => false,
+        _ => utf8_locale()
+    };
+    let graph = if unicode {
+        let cells = (fill * (width * 8) as f64).round() as usize;
+        let mut filled = "█".repeat(cells / 8);
+        if !cells.is_multiple_of(8) {
+            filled.push(BAR_EIGHTHS[cells % 8]);
+        }
+        let len = filled.chars().count();
+        theme::current().bar(&filled, &"░".repeat(width - len), level)
+    }
+    else {
+        let inner = width - 2;
+        let cells = (fill * inner as f64).round() as usize;
+        theme::current().bar(&format!("[{}", "#".repeat(cells)), &format!("{}]", "-".repeat(inner - cells)), level)
+    };
+    format!("{} {}", graph, to_level(&pct_text, level))
+}
+
+fn with_bar(config: &Config, pad: usize, value: String, pct: f64, level: Level) -> String {
+    if !config.bars.enabled {
+        return value;
+    }
+    let padding = PADDING_VALUE.saturating_sub(visible_width(&value));
+    format!("{}{} {}", value, " ".repeat(padding), bar(config, pad, pct, level))
+}
+
+fn usage(config: &Config, pad: usize, used: u64, total: u64, level: Level) -> String {
+    let value = format!("{} / {}", to_level(&byte2str(used, true), level), byte2str(total, true));
+    with_bar(config, pad, value, percent(used, total), level)
+}
+
+// Apply the theme's label and value styles to rows laid out as marker, label and value.
+fn paint_rows(text: &str, pad: usize) -> String {
+    let theme = theme::current();
+    if !theme.styles_rows() {
+        return text.to_string();
+    }
+    let mut painted = String::new();
+    for line in text.lines() {
+        let split = line.char_indices().nth(PADDING_BEFORE + pad).map(|(i, _)| i).unwrap_or(line.len());
+        let start = line.char_indices().nth(PADDING_BEFORE).map(|(i, _)| i).unwrap_or(line.len()).min(split);
+        let (label, value) = (&line[start..split], &line[split..]);
+        let name = label.trim_end();
+        writeln!(&mut painted, "{}{}{}{}", &line[..start], theme.label(name), &label[name.len()..], theme.value(value)).unwrap();
+    }
+    painted
+}
+
+fn degraded(text: &mut String, stats: &SystemStats, section: &str, label: &str, pad: usize) -> bool {
+    let status = match stats.status.get(section) {
+        Some(status) if !status.is_ok() => status,
+        _ => return false
+    };
+    let level = if matches!(status, SectionStatus::Error(_)) { Level::Critical } else { Level::Warn };
+    writeln!(
+        text,
+        "{:<padb$}{:<pada$}{}",
+        marker(level),
+        ellipsize(label, pad - 1),
+        to_level(&status.to_string(), level),
+        padb = PADDING_BEFORE,
+        pada = pad
+    )
+    .unwrap();
+    true
+}
+
+fn header_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
     let mut text = String::new();
-    text += "\n";
+    if !config.header.enabled || degraded(&mut text, stats, "identity", "Host", pad) {
+        return text;
+    }
+    let identity = &stats.identity;
+    let banner = if config.header.banner { banner::render(&identity.hostname, &config.header.font) } else { Ok(Vec::new()) };
+    match banner {
+        Ok(rows) if !rows.is_empty() && rows.iter().all(|row| PADDING_BEFORE + visible_width(row) <= term_width()) => {
+            for row in rows {
+                writeln!(&mut text, "{:<padb$}{}", "", to_heading(&row), padb = PADDING_BEFORE).unwrap();
+            }
+            writeln!(&mut text).unwrap();
+        }
+        // No banner wanted, or it would wrap on this terminal.
+        Ok(_) => writeln!(&mut text, "{:<padb$}{}\n", "", to_bold(&identity.hostname), padb = PADDING_BEFORE).unwrap(),
+        Err(e) => writeln!(
+            &mut text,
+            "{:<padb$}{}\n{:<padb$}{:<pada$}{}",
+            "",
+            to_bold(&identity.hostname),
+            marker(Level::Warn),
+            "Font",
+            to_level(&e, Level::Warn),
+            padb = PADDING_BEFORE,
+            pada = pad
+        )
+        .unwrap()
+    }
+    let mut rows = String::new();
+    for (label, value) in [
+        ("OS", &identity.os),
+        ("Kernel", &identity.kernel),
+        ("Arch", &identity.arch),
+        ("Virtualization", &identity.virtualization),
+    ] {
+        writeln!(&mut rows, "{:<padb$}{:<pada$}{}", "", label, value, padb = PADDING_BEFORE, pada = pad).unwrap();
+    }
+    text + &paint_rows(&rows, pad)
+}
 
-    if config.memory {
+fn memory_details(text: &mut String, config: &Config, stats: &SystemStats, pad: usize) {
+    let (show, details) = (&config.memory_details, &stats.memory.details);
+    for swap in &details.swaps {
+        let level = config.thresholds.swap.level(percent(swap.used, swap.size));
+        writeln!(
+            text,
+            "{:<padb$}{:<indent$}{:<pada$}{}",
+            marker(level),
+            "",
+            ellipsize(&swap.name, pad - PADDING_INDENT - 1),
+            usage(config, pad, swap.used, swap.size, level),
+            indent = PADDING_INDENT,
+            padb = PADDING_BEFORE,
+            pada = pad - PADDING_INDENT
+        )
+        .unwrap();
+    }
+
+    let mut rows = Vec::new();
+    if show.dirty {
+        rows.push(("", "Dirty".to_string(), format!("{}, {} writeback", byte2str(details.dirty, true), byte2str(details.writeback, true))));
+    }
+    if show.shmem {
+        rows.push(("", "Shmem".to_string(), byte2str(details.shmem, true)));
+    }
+    if show.slab {
+        let slab = format!("{} reclaimable, {} unreclaimable", byte2str(details.sreclaimable, true), byte2str(details.sunreclaim, true));
+        rows.push(("", "Slab".to_string(), slab));
+    }
+    if show.hugepages {
+        let hugepages = format!("{} of {} free, {} pages", details.hugepages_free, details.hugepages_total, byte2str(details.hugepage_size, true));
+        rows.push(("", "Hugepages".to_string(), hugepages));
+    }
+    if show.commit {
+        // Only enforced with vm.overcommit_memory = 2, otherwise a hint of overcommitment.
+        let level = if details.committed > details.commit_limit { Level::Warn } else { Level::Ok };
+        rows.push((marker(level), "Committed".to_string(), usage(config, pad, details.committed, details.commit_limit, level)));
+    }
+    for zram in &details.zram {
+        let value = format!(
+            "{} in {} ({:.2}x), {} used",
+            byte2str(zram.orig, true),
+            byte2str(zram.compressed, true),
+            zram.ratio(),
+            byte2str(zram.mem_used, true)
+        );
+        rows.push(("", ellipsize(&zram.name, pad - 1), value));
+    }
+    for (marker, label, value) in rows {
+        writeln!(text, "{:<padb$}{:<pada$}{}", marker, label, value, padb = PADDING_BEFORE, pada = pad).unwrap();
+    }
+}
+
+fn memory_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if config.memory && !degraded(&mut text, stats, "memory", "Memory", pad) {
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{} / {}",
+            "{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}\n{:<padb$}{:<pada$}{}",
             "",
             "Active", byte2str(stats.memory.active_mem, true),
             "",
-            "Total", byte2str(stats.memory.total_mem, true),
-            "",
-            "Available", byte2str(stats.memory.available_mem, true),
+            "Total",
+            with_bar(
+                config,
+                pad,
+                byte2str(stats.memory.total_mem, true),
+                percent(stats.memory.total_mem - stats.memory.available_mem, stats.memory.total_mem),
+                stats.memory.level
+            ),
+            marker(stats.memory.level),
+            "Available", to_level(&byte2str(stats.memory.available_mem, true), stats.memory.level),
             "",
             "Free", byte2str(stats.memory.free_mem, true),
             "",
             "Buffer", byte2str(stats.memory.buffer, true),
             "",
             "Cache", byte2str(stats.memory.cache, true),
-            "",
+            marker(stats.memory.swap_level),
             "Swap",
-            byte2str(stats.memory.total_swap - stats.memory.free_swap, true),
-            byte2str(stats.memory.total_swap, true),
+            usage(config, pad, stats.memory.total_swap - stats.memory.free_swap, stats.memory.total_swap, stats.memory.swap_level),
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
         )
         .unwrap();
+        memory_details(&mut text, config, stats, pad);
     }
 
     for gpu in &stats.gpus {
+        let level = config.thresholds.memory.level(percent(gpu.used_vram, gpu.total_vram));
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{} / {}",
-            "",
-            gpu.mem_name,
-            byte2str(gpu.used_vram, true),
-            byte2str(gpu.total_vram, true),
+            "{:<padb$}{:<pada$}{}",
+            marker(level),
+            ellipsize(&gpu.mem_name, pad - 1),
+            usage(config, pad, gpu.used_vram, gpu.total_vram, level),
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
         )
         .unwrap();
     }
-    if !stats.gpus.is_empty() || config.memory {
-        writeln!(&mut text).unwrap()
-    };
+    if !config.gpu.is_empty() {
+        degraded(&mut text, stats, "gpus", "GPU", pad);
+    }
+    text
+}
+
+fn cpu_breakdown(usage: &CpuUsage) -> String {
+    format!("user {:.1}%, system {:.1}%, iowait {:.1}%, steal {:.1}%, idle {:.1}%", usage.user, usage.system, usage.iowait, usage.steal, usage.idle)
+}
+
+// One cell per core, from idle to fully busy, wrapped to the width of the value column.
+fn heat_strip(cores: &[CpuUsage], pad: usize) -> Vec<String> {
+    let cells: Vec<char> = if utf8_locale() { "▁▂▃▄▅▆▇█".chars().collect() } else { "_.-:=+*#".chars().collect() };
+    let width = term_width().saturating_sub(PADDING_BEFORE + pad).max(8);
+    cores
+        .chunks(width)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|core| {
+                    let cell = ((core.busy() / 100.0 * cells.len() as f64) as usize).min(cells.len() - 1);
+                    to_level(&cells[cell].to_string(), core.level)
+                })
+                .collect()
+        })
+        .collect()
+}
 
-    if config.cpuload {
+fn pressure_avgs(avg: &PressureAvg) -> String { format!("{:>5.2} {:>5.2} {:>5.2}", avg.avg10, avg.avg60, avg.avg300) }
+
+fn pressure_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.psi.enabled {
+        return text;
+    }
+    // Service pressure can fail on its own, the system rows are kept then.
+    if stats.pressure.is_empty() {
+        degraded(&mut text, stats, "pressure", "Pressure", pad);
+        return text;
+    }
+    writeln!(&mut text, "{:<padb$}{:<pada$}some avg10/60/300   full avg10/60/300", "", "Pressure", padb = PADDING_BEFORE, pada = pad).unwrap();
+    let mut scope = "system";
+    for pressure in &stats.pressure {
+        let indent = if pressure.scope == "system" { 0 } else { PADDING_INDENT };
+        if pressure.scope != scope {
+            scope = &pressure.scope;
+            writeln!(&mut text, "{:<padb$}{}", "", ellipsize(scope, pad - 1), padb = PADDING_BEFORE).unwrap();
+        }
+        let full = pressure.full.as_ref().map(pressure_avgs).unwrap_or_else(|| "-".to_string());
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{:.2}%\n{:<padb$}{:<pada$}{:.2}%\n{:<padb$}{:<pada$}{:.2}%",
-            "",
-            " 1 min",
-            stats.load_avg.one,
+            "{:<padb$}{:<indent$}{:<pada$}{}   {}",
+            marker(pressure.level),
             "",
-            " 5 mins",
-            stats.load_avg.five,
-            "",
-            "15 mins",
-            stats.load_avg.fifteen,
+            pressure.resource,
+            to_level(&pressure_avgs(&pressure.some), pressure.level),
+            full,
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER,
+            pada = pad - indent
         )
         .unwrap();
-        writeln!(&mut text).unwrap();
     }
+    degraded(&mut text, stats, "pressure", "Services", pad);
+    text
+}
 
+fn cpu_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.cpu.enabled || degraded(&mut text, stats, "cpu", "CPU", pad) {
+        return text;
+    }
+    let total = &stats.cpu.total;
+    let busy = with_bar(config, pad, to_level(&format!("{:.1}%", total.busy()), total.level), total.busy(), total.level);
+    writeln!(&mut text, "{:<padb$}{:<pada$}{}", marker(total.level), "CPU", busy, padb = PADDING_BEFORE, pada = pad).unwrap();
+    writeln!(&mut text, "{:<padb$}{:<pada$}{}", "", "", cpu_breakdown(total), padb = PADDING_BEFORE, pada = pad).unwrap();
+    match config.cpu.per_core.as_str() {
+        "strip" => {
+            for (i, strip) in heat_strip(&stats.cpu.cores, pad).iter().enumerate() {
+                let label = if i == 0 { "Cores" } else { "" };
+                writeln!(&mut text, "{:<padb$}{:<pada$}{}", "", label, strip, padb = PADDING_BEFORE, pada = pad).unwrap();
+            }
+        }
+        "rows" => {
+            for core in &stats.cpu.cores {
+                let busy = format!("{:.1}%", core.busy());
+                let value = format!("{}{} {}", to_level(&busy, core.level), " ".repeat(6 - busy.len().min(6)), cpu_breakdown(core));
+                writeln!(&mut text, "{:<padb$}{:<pada$}{}", marker(core.level), core.name, value, padb = PADDING_BEFORE, pada = pad).unwrap();
+            }
+        }
+        _ => {}
+    }
+    text
+}
+
+fn ghz(khz: u64) -> String { format!("{:.2} GHz", khz as f64 / 1e6) }
+
+fn range(values: impl Iterator<Item = u64> + Clone) -> Option<String> {
+    let (low, high) = (values.clone().min()?, values.max()?);
+    Some(if low == high { ghz(low) } else { format!("{} - {}", ghz(low), ghz(high)) })
+}
+
+fn cpu_info_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.cpuinfo || degraded(&mut text, stats, "cpu_info", "CPU", pad) {
+        return text;
+    }
+    let info = &stats.cpu_info;
+    let plural = |n: usize, what: &str| format!("{} {}{}", n, what, if n == 1 { "" } else { "s" });
+    let mut rows = vec![
+        ("", "Model", info.model.clone()),
+        ("", "Topology", format!("{}, {}, {}", plural(info.sockets, "socket"), plural(info.cores, "core"), plural(info.threads, "thread"))),
+    ];
+    if let Some(current) = range(info.freqs.iter().map(|f| f.cur)) {
+        let limits = range(info.freqs.iter().flat_map(|f| [f.min, f.max]).filter(|khz| *khz > 0));
+        rows.push(("", "Frequency", limits.map(|limits| format!("{} (limits {})", current, limits)).unwrap_or(current)));
+    }
+    if !info.governors.is_empty() {
+        let mut governor = to_level(&info.governors.join(", "), info.governor_level);
+        if !info.epp.is_empty() {
+            governor += &format!(" (epp {})", info.epp.join(", "));
+        }
+        rows.push((marker(info.governor_level), "Governor", governor));
+    }
+    if !info.offline.is_empty() {
+        let offline = info.offline.iter().map(|cpu| format!("cpu{}", cpu)).collect::<Vec<_>>().join(", ");
+        rows.push((marker(Level::Warn), "Offline", to_level(&offline, Level::Warn)));
+    }
+    for (marker, label, value) in rows {
+        writeln!(&mut text, "{:<padb$}{:<pada$}{}", marker, label, value, padb = PADDING_BEFORE, pada = pad).unwrap();
+    }
+    text
+}
+
+fn load_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if config.cpuload && !degraded(&mut text, stats, "load_avg", "Load", pad) {
+        let load_avg = &stats.load_avg;
+        // A cgroup quota can leave a fraction of a CPU.
+        let cpus = format!("{:.2}", load_avg.cpus).trim_end_matches('0').trim_end_matches('.').to_string();
+        for (label, load, level) in [
+            (" 1 min", load_avg.one, load_avg.one_level),
+            (" 5 mins", load_avg.five, load_avg.five_level),
+            ("15 mins", load_avg.fifteen, load_avg.fifteen_level),
+        ] {
+            let value = format!("{:.2} ({:.0}% of {} {})", load, load_avg.per_cpu(load), cpus, if cpus == "1" { "CPU" } else { "CPUs" });
+            writeln!(&mut text, "{:<padb$}{:<pada$}{}", marker(level), label, to_level(&value, level), padb = PADDING_BEFORE, pada = pad).unwrap();
+        }
+    }
+    text
+}
+
+fn temp_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
     if config.cputemp != "none" {
-        for (name, temp) in &stats.cpu_temp {
+        degraded(&mut text, stats, "cpu_temp", "CPU", pad);
+        for temp in &stats.cpu_temp {
             writeln!(
                 &mut text,
-                "{:<padb$}{:<pada$}{:.2} °C",
-                "",
-                name,
-                *temp as f64 / 1000.0,
+                "{:<padb$}{:<pada$}{}",
+                marker(temp.level),
+                ellipsize(&temp.name, pad - 1),
+                to_level(&format!("{:.2} °C", temp.temp), temp.level),
                 padb = PADDING_BEFORE,
-                pada = PADDING_AFTER
+                pada = pad
             )
             .unwrap();
         }
@@ -100,131 +429,368 @@ pub fn generate_text(config: &Config, stats: &SystemStats) -> String {
     for gpu in &stats.gpus {
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{:.2} °C",
-            "",
-            gpu.temp_name,
-            gpu.temp as f64,
+            "{:<padb$}{:<pada$}{}",
+            marker(gpu.temp_level),
+            ellipsize(&gpu.temp_name, pad - 1),
+            to_level(&format!("{:.2} °C", gpu.temp as f64), gpu.temp_level),
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
         )
         .unwrap();
     }
-    if !stats.cpu_temp.is_empty() || !stats.gpus.is_empty() {
-        writeln!(&mut text).unwrap()
-    };
+    text
+}
 
-    if config.uptime {
+fn uptime_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if config.uptime && !degraded(&mut text, stats, "uptime", "Uptime", pad) {
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{}\n",
+            "{:<padb$}{:<pada$}{}",
             "",
             "Uptime",
             s2time(stats.uptime),
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
         )
         .unwrap();
     }
+    text
+}
 
-    if config.lastlogin {
+fn last_login_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if config.lastlogin && !degraded(&mut text, stats, "last_login", "Last", pad) {
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{}@{}\n",
+            "{:<padb$}{:<pada$}{}@{}",
             "",
             "Last",
             stats.last_login.user,
             stats.last_login.host,
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
         )
         .unwrap();
     }
+    text
+}
 
+fn disk_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.disk.is_empty() {
+        degraded(&mut text, stats, "disks", "Disks", pad);
+    }
     for disk in &stats.disks {
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{} / {}",
-            "",
-            disk.name,
-            byte2str(disk.used, true),
-            byte2str(disk.total, true),
+            "{:<padb$}{:<pada$}{}",
+            marker(disk.level),
+            ellipsize(&disk.name, pad - 1),
+            usage(config, pad, disk.used, disk.total, disk.level),
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER,
+            pada = pad,
         )
         .unwrap();
-        for (subvol_name, subvol_size) in &disk.subvol {
+        for subvol in &disk.subvol {
             writeln!(
                 &mut text,
                 "{:<padb$}{:<padi$}{:<pada$}{}",
                 "",
                 "",
-                subvol_name,
-                byte2str(*subvol_size, true),
+                ellipsize(&subvol.name, pad - PADDING_INDENT - 1),
+                byte2str(subvol.used, true),
                 padb = PADDING_BEFORE,
-                pada = PADDING_AFTER - PADDING_INDENT,
+                pada = pad - PADDING_INDENT,
                 padi = PADDING_INDENT
             )
             .unwrap();
         }
     }
-    if !stats.disks.is_empty() {
-        writeln!(&mut text).unwrap()
-    };
+    text
+}
 
-    // Services
+fn service_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.systemctl.is_empty() {
+        degraded(&mut text, stats, "services", "Services", pad);
+    }
     for service in &stats.services {
         writeln!(
             &mut text,
-            "{:<padb$}{:<pada$}{:<padm$}{}:{}",
-            "",
-            service.name,
+            "{:<padb$}{:<pada$}{:<padm$}{}",
+            marker(service.level),
+            ellipsize(&service.name, pad - 1),
             byte2str(service.memory, true),
-            service.state,
-            service.substate,
+            to_level(&format!("{}:{}", service.state, service.substate), service.level),
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER,
+            pada = pad,
             padm = PADDING_MEMORY
         )
         .unwrap();
     }
-    if !stats.services.is_empty() {
-        writeln!(&mut text).unwrap()
-    };
+    text
+}
 
-    // Dockers
+fn docker_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.docker.is_empty() {
+        degraded(&mut text, stats, "dockers", "Docker", pad);
+    }
     for docker in &stats.dockers {
         writeln!(
             &mut text,
             "{:<padb$}{:<pada$}{}: {}",
             "",
-            docker.name,
+            ellipsize(&docker.name, pad - 1),
             docker.state,
             docker.status,
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
         )
         .unwrap();
     }
-    if !stats.dockers.is_empty() {
-        writeln!(&mut text).unwrap()
-    };
+    text
+}
 
+fn vm_section(config: &Config, stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if !config.vm.is_empty() {
+        degraded(&mut text, stats, "vms", "VMs", pad);
+    }
     for vm in &stats.vms {
-        writeln!(
+        write!(
             &mut text,
             "{:<padb$}{:<pada$}{}, {} cpu(s), {} / {}, {}",
-            "",
-            vm.name,
-            vm.state,
+            marker(vm.level),
+            ellipsize(&vm.name, pad - 1),
+            to_level(&vm.state, vm.level),
             vm.cpus,
             byte2str(vm.used_mem, true),
             byte2str(vm.total_mem, true),
             if vm.autostart == "enable" { "autostart" } else { "no autostart" },
             padb = PADDING_BEFORE,
-            pada = PADDING_AFTER
+            pada = pad
+        )
+        .unwrap();
+        if config.bars.enabled {
+            let pct = percent(vm.used_mem, vm.total_mem);
+            write!(&mut text, " {}", bar(config, pad, pct, config.thresholds.memory.level(pct))).unwrap();
+        }
+        writeln!(&mut text).unwrap();
+    }
+    text
+}
+
+fn snapshot_section(stats: &SystemStats, pad: usize) -> String {
+    let mut text = String::new();
+    if stats.cached {
+        writeln!(
+            &mut text,
+            "{:<padb$}{:<pada$}{} ago",
+            "",
+            "Snapshot",
+            s2time(snapshot_age(stats)),
+            padb = PADDING_BEFORE,
+            pada = pad
+        )
+        .unwrap();
+    }
+    text
+}
+
+fn plugin_section(stats: &SystemStats, plugin: &PluginSection, pad: usize) -> String {
+    let mut text = String::new();
+    degraded(&mut text, stats, &format!("plugin.{}", plugin.name), &plugin.title, pad);
+    for row in &plugin.rows {
+        writeln!(
+            &mut text,
+            "{:<padb$}{:<pada$}{}",
+            marker(row.severity),
+            ellipsize(&row.label, pad - 1),
+            to_level(&row.value_text(), row.severity),
+            padb = PADDING_BEFORE,
+            pada = pad
         )
         .unwrap();
     }
+    text
+}
+
+const COLUMN_GAP: usize = 3;
+
+fn chars(s: &str) -> usize { s.chars().count() }
+
+// Widest label in use plus a space, so long service or disk names don't push their values out
+// of line. Labels take at most a third of the terminal and longer ones are ellipsized.
+fn label_width(stats: &SystemStats) -> usize {
+    let longest = stats
+        .cpu_temp
+        .iter()
+        .map(|temp| chars(&temp.name))
+        .chain(stats.gpus.iter().flat_map(|gpu| [chars(&gpu.mem_name), chars(&gpu.temp_name)]))
+        .chain(stats.disks.iter().map(|disk| chars(&disk.name)))
+        .chain(stats.disks.iter().flat_map(|disk| disk.subvol.iter().map(|subvol| chars(&subvol.name) + PADDING_INDENT)))
+        .chain(stats.services.iter().map(|service| chars(&service.name)))
+        .chain(stats.pressure.iter().map(|pressure| chars(&pressure.scope)))
+        .chain(stats.memory.details.swaps.iter().map(|swap| chars(&swap.name) + PADDING_INDENT))
+        .chain(stats.memory.details.zram.iter().map(|zram| chars(&zram.name)))
+        .chain(stats.dockers.iter().map(|docker| chars(&docker.name)))
+        .chain(stats.vms.iter().map(|vm| chars(&vm.name)))
+        .chain(stats.plugins.iter().flat_map(|plugin| plugin.rows.iter().map(|row| chars(&row.label))))
+        .max()
+        .unwrap_or(0);
+    (longest + 1).clamp(PADDING_AFTER, (term_width() / 3).max(PADDING_AFTER))
+}
+
+// Every section as (config key, title, text) in the built-in order, including empty ones.
+fn all_sections(config: &Config, stats: &SystemStats, pad: usize) -> Vec<(String, String, String)> {
+    let mut sections = vec![
+        ("header", "Host", header_section(config, stats, pad)),
+        ("memory", "Memory", memory_section(config, stats, pad)),
+        ("cpuload", "Load", load_section(config, stats, pad)),
+        ("psi", "Pressure", pressure_section(config, stats, pad)),
+        ("cpu", "CPU", cpu_section(config, stats, pad)),
+        ("cpuinfo", "CPU info", cpu_info_section(config, stats, pad)),
+        ("cputemp", "Temperature", temp_section(config, stats, pad)),
+        ("uptime", "Uptime", uptime_section(config, stats, pad)),
+        ("lastlogin", "Last login", last_login_section(config, stats, pad)),
+        ("disk", "Disks", disk_section(config, stats, pad)),
+        ("systemctl", "Services", service_section(config, stats, pad)),
+        ("docker", "Docker", docker_section(config, stats, pad)),
+        ("vm", "VMs", vm_section(config, stats, pad)),
+    ]
+    .into_iter()
+    .map(|(key, title, text)| (key.to_string(), title.to_string(), text))
+    .collect::<Vec<_>>();
+    for plugin in &stats.plugins {
+        sections.push((format!("plugin.{}", plugin.name), plugin.title.clone(), plugin_section(stats, plugin, pad)));
+    }
+    sections.push(("snapshot".to_string(), "Snapshot".to_string(), snapshot_section(stats, pad)));
+    // The header paints its own rows, its banner is not laid out in columns.
+    sections.into_iter().map(|(key, title, text)| if key == "header" { (key, title, text) } else { (key, title, paint_rows(&text, pad)) }).collect()
+}
+
+fn layout(config: &Config) -> Vec<LayoutGroup> {
+    if !config.layout.is_empty() {
+        return config.layout.clone();
+    }
+    // The header goes above the rest rather than being flowed into a column with it.
+    let (header, rest) = SECTION_ORDER.split_at(1);
+    [header, rest]
+        .iter()
+        .map(|names| LayoutGroup {
+            heading:  "none".to_string(),
+            sections: names.iter().map(|name| LayoutSection::Name(name.to_string())).collect(),
+            columns:  false
+        })
+        .collect()
+}
+
+fn empty_section(title: &str, pad: usize) -> String {
+    paint_rows(&format!("{:<padb$}{:<pada$}none\n", "", title, padb = PADDING_BEFORE, pada = pad), pad)
+}
+
+// Sections of a layout group in order, where "plugin" stands for every plugin.
+fn group_sections(group: &LayoutGroup, sections: &[(String, String, String)], pad: usize) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    for entry in &group.sections {
+        let name = entry.name();
+        for (_, title, text) in sections.iter().filter(|(key, _, _)| key == name || (name == "plugin" && key.starts_with("plugin."))) {
+            if !text.is_empty() {
+                result.push((title.clone(), text.clone()));
+            }
+            else if !entry.hide_empty() {
+                result.push((title.clone(), empty_section(title, pad)));
+            }
+        }
+    }
+    result
+}
+
+// Lay the blocks out next to each other, or None if they do not fit in `width` columns.
+fn side_by_side(blocks: &[String], width: usize) -> Option<String> {
+    let columns: Vec<Vec<&str>> = blocks.iter().map(|block| block.lines().collect()).collect();
+    let widths: Vec<usize> = columns.iter().map(|lines| lines.iter().map(|line| visible_width(line)).max().unwrap_or(0)).collect();
+    if widths.iter().sum::<usize>() + COLUMN_GAP * blocks.len().saturating_sub(1) > width {
+        return None;
+    }
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    let mut text = String::new();
+    for row in 0..rows {
+        let mut line = String::new();
+        for (i, lines) in columns.iter().enumerate() {
+            let cell = lines.get(row).copied().unwrap_or("");
+            line += cell;
+            if i + 1 < columns.len() {
+                line += &" ".repeat(widths[i] - visible_width(cell) + COLUMN_GAP);
+            }
+        }
+        writeln!(&mut text, "{}", line.trim_end()).unwrap();
+    }
+    Some(text)
+}
+
+// Spread the blocks over as many columns as fit, up to `max_columns`, keeping their order and
+// balancing the column heights. None if not even two columns fit.
+fn flow(blocks: &[String], width: usize, max_columns: usize) -> Option<String> {
+    let heights: Vec<usize> = blocks.iter().map(|block| block.lines().count() + 1).collect();
+    let total: usize = heights.iter().sum();
+    for count in (2..=max_columns.min(blocks.len())).rev() {
+        let target = total.div_ceil(count);
+        let mut columns = vec![String::new()];
+        let mut height = 0;
+        for (block, lines) in blocks.iter().zip(&heights) {
+            if height >= target && columns.len() < count {
+                columns.push(String::new());
+                height = 0;
+            }
+            let column = columns.last_mut().unwrap();
+            if !column.is_empty() {
+                *column += "\n";
+            }
+            *column += block;
+            height += lines;
+        }
+        if let Some(text) = side_by_side(&columns, width) {
+            return Some(text);
+        }
+    }
+    None
+}
+
+pub fn generate_sections(config: &Config, stats: &SystemStats) -> Vec<(String, String)> {
+    let pad = label_width(stats);
+    let sections = all_sections(config, stats, pad);
+    layout(config).iter().flat_map(|group| group_sections(group, &sections, pad)).collect()
+}
+
+pub fn generate_text(config: &Config, stats: &SystemStats) -> String {
+    let mut text = String::new();
+    text += "\n";
+
+    let pad = label_width(stats);
+    let sections = all_sections(config, stats, pad);
+    for group in layout(config) {
+        let blocks: Vec<String> = group_sections(&group, &sections, pad).into_iter().map(|(_, block)| block).collect();
+        if blocks.is_empty() {
+            continue;
+        }
+        if group.heading != "none" {
+            text += &to_heading(&group.heading);
+            text += "\n";
+        }
+        let columns = if group.columns { side_by_side(&blocks, term_width()) } else { flow(&blocks, term_width(), config.columns) };
+        if let Some(columns) = columns {
+            text += &columns;
+            text += "\n";
+            continue;
+        }
+        for block in blocks {
+            text += &block;
+            text += "\n";
+        }
+    }
 
     text
 }
@@ -0,0 +1,162 @@
+// run  := cargo run --
+// dir  := .
+// kid  :=
+
+// Plugin protocol (version 1)
+//
+// A `[[plugin]]` entry names an executable that is run once per collection. It receives a
+// JSON request on stdin:
+//
+//     {"version": 1, "name": "backup", "options": { ...the entry's `options` table... }}
+//
+// and must print a single JSON document on stdout and exit with status 0:
+//
+//     {
+//       "title": "Backups",
+//       "rows": [
+//         {"label": "nightly", "value": 3.5, "unit": "h", "severity": "ok"},
+//         {"label": "offsite", "value": "stale", "severity": "critical"}
+//       ]
+//     }
+//
+// `title` defaults to the plugin name, `unit` to an empty string and `severity` (ok, warn or
+// critical) to ok. A missing executable, a non-zero exit, invalid JSON or running past the
+// plugin's `timeout` marks the section as degraded instead of failing the whole MOTD.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::collectors::{section, Collector};
+use crate::error::{deadline, output_until, own_group, CollectError, SectionStatus};
+use crate::load_config::{SysPlugin, Timeouts};
+use crate::thresholds::Level;
+
+pub const PROTOCOL_VERSION: u64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PluginRow {
+    pub label:    String,
+    pub value:    Value,
+    #[serde(default)]
+    pub unit:     String,
+    #[serde(default)]
+    pub severity: Level
+}
+
+#[derive(Deserialize)]
+struct PluginOutput {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    rows:  Vec<PluginRow>
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PluginSection {
+    pub name:  String,
+    pub title: String,
+    pub rows:  Vec<PluginRow>
+}
+
+impl PluginRow {
+    pub fn value_text(&self) -> String {
+        let value = match &self.value {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            other => other.to_string()
+        };
+        if self.unit.is_empty() {
+            value
+        }
+        else {
+            format!("{} {}", value, self.unit)
+        }
+    }
+}
+
+pub fn plugin_name(plugin: &SysPlugin) -> String {
+    if plugin.name != "none" {
+        return plugin.name.clone();
+    }
+    Path::new(&plugin.command).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| plugin.command.clone())
+}
+
+impl PluginSection {
+    pub fn new(plugin: &SysPlugin) -> Self {
+        let name = plugin_name(plugin);
+        let title = if plugin.display != "none" { plugin.display.clone() } else { name.clone() };
+        PluginSection { name, title, rows: Vec::new() }
+    }
+}
+
+pub fn run_plugin(plugin: &SysPlugin) -> Result<PluginSection, CollectError> {
+    let mut result = PluginSection::new(plugin);
+    let mut child = own_group(Command::new(&plugin.command).args(&plugin.args))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => CollectError::Unavailable(format!("{} not found", plugin.command)),
+            _ => CollectError::Failed(format!("{}: {}", plugin.command, e))
+        })?;
+
+    let request = json!({ "version": PROTOCOL_VERSION, "name": result.name, "options": plugin.options });
+    if let Some(mut stdin) = child.stdin.take() {
+        // Plugins are free to ignore the request, so a closed pipe is not an error.
+        let _ = writeln!(stdin, "{}", request);
+    }
+
+    let output = output_until(child, deadline())
+        .map_err(|e| CollectError::Failed(format!("{}: {}", plugin.command, e)))?
+        .ok_or(CollectError::TimedOut)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("exited with an error");
+        return Err(CollectError::Failed(format!("{}: {}", result.name, message)));
+    }
+
+    let parsed: PluginOutput = serde_json::from_slice(&output.stdout)
+        .map_err(|e| CollectError::Failed(format!("{}: invalid plugin output: {}", result.name, e)))?;
+    if plugin.display == "none" {
+        if let Some(title) = parsed.title {
+            result.title = title;
+        }
+    }
+    result.rows = parsed.rows;
+    Ok(result)
+}
+
+pub struct PluginCollector {
+    pub plugin: SysPlugin,
+    key:        String
+}
+
+impl PluginCollector {
+    pub fn new(plugin: &SysPlugin) -> Self { PluginCollector { key: format!("plugin.{}", plugin_name(plugin)), plugin: plugin.clone() } }
+}
+
+impl Collector for PluginCollector {
+    type Output = PluginSection;
+
+    fn name(&self) -> &str { &self.key }
+
+    fn section(&self) -> &str { "plugin" }
+
+    fn timeout(&self, timeouts: &Timeouts) -> Duration { timeouts.section(self.plugin.timeout) }
+
+    fn collect(&self) -> (PluginSection, SectionStatus) {
+        let (data, status) = section(run_plugin(&self.plugin));
+        if status.is_ok() {
+            (data, status)
+        }
+        else {
+            (PluginSection::new(&self.plugin), status)
+        }
+    }
+}
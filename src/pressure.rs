@@ -0,0 +1,132 @@
+// run  := cargo run -- -f json
+// dir  := .
+// kid  :=
+
+// Pressure stall information from /proc/pressure and cgroup v2 `*.pressure` files.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::collectors::{partial, Collector};
+use crate::error::{run_command, CollectError, SectionStatus};
+use crate::load_config::{Config, SysService, Threshold};
+use crate::thresholds::Level;
+
+pub const RESOURCES: [&str; 3] = ["cpu", "memory", "io"];
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy)]
+pub struct PressureAvg {
+    pub avg10:  f64,
+    pub avg60:  f64,
+    pub avg300: f64
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pressure {
+    // "system" or the service name.
+    pub scope:    String,
+    pub resource: String,
+    pub some:     PressureAvg,
+    pub full:     Option<PressureAvg>,
+    pub level:    Level
+}
+
+fn parse_avg(line: &str) -> PressureAvg {
+    let mut avg = PressureAvg::default();
+    for (key, value) in line.split_whitespace().filter_map(|field| field.split_once('=')) {
+        let value = value.parse().unwrap_or(0.0);
+        match key {
+            "avg10" => avg.avg10 = value,
+            "avg60" => avg.avg60 = value,
+            "avg300" => avg.avg300 = value,
+            _ => {}
+        }
+    }
+    avg
+}
+
+fn read_pressure(path: &Path, scope: &str, resource: &str) -> Result<Pressure, CollectError> {
+    let content = fs::read_to_string(path)?;
+    let line = |kind: &str| content.lines().find_map(|line| line.strip_prefix(kind)).map(parse_avg);
+    Ok(Pressure {
+        scope:    scope.to_string(),
+        resource: resource.to_string(),
+        some:     line("some ").ok_or_else(|| CollectError::Failed(format!("{}: no some line", path.display())))?,
+        full:     line("full "),
+        level:    Level::Ok
+    })
+}
+
+fn service_cgroup(service: &SysService) -> Result<String, CollectError> {
+    let output = run_command(Command::new("systemctl").arg("show").arg(&service.name).arg("--property=ControlGroup"))?;
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("ControlGroup="))
+        .map(str::to_string)
+        .filter(|cgroup| !cgroup.is_empty())
+        .ok_or_else(|| CollectError::Failed(format!("{} has no cgroup", service.name)))
+}
+
+pub fn get_pressure(services: &[SysService]) -> (Vec<Pressure>, Option<CollectError>) {
+    let mut pressures = Vec::new();
+    for resource in RESOURCES {
+        match read_pressure(&Path::new("/proc/pressure").join(resource), "system", resource) {
+            Ok(pressure) => pressures.push(pressure),
+            Err(e) => return (Vec::new(), Some(e))
+        }
+    }
+
+    let mut error = None;
+    for service in services {
+        let name = if service.display != "none" { &service.display } else { &service.name };
+        // The unified hierarchy sits below /sys/fs/cgroup/unified on hybrid setups.
+        let dir = service_cgroup(service).and_then(|cgroup| {
+            ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"]
+                .iter()
+                .map(|root| format!("{}{}", root, cgroup))
+                .find(|dir| Path::new(dir).join("cpu.pressure").exists())
+                .ok_or_else(|| CollectError::Unavailable(format!("no pressure files for {}", service.name)))
+        });
+        let result = dir.and_then(|dir| {
+            RESOURCES.iter().map(|resource| read_pressure(&Path::new(&dir).join(format!("{}.pressure", resource)), name, resource)).collect()
+        });
+        match result {
+            Ok(service_pressures) => pressures.extend::<Vec<_>>(service_pressures),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+    (pressures, error)
+}
+
+pub struct PressureCollector {
+    pub services:  Vec<SysService>,
+    pub threshold: Threshold
+}
+
+impl PressureCollector {
+    pub fn new(config: &Config) -> Self {
+        let services = if config.psi.services { config.systemctl.clone() } else { Vec::new() };
+        PressureCollector { services, threshold: config.thresholds.psi }
+    }
+}
+
+impl Collector for PressureCollector {
+    type Output = Vec<Pressure>;
+
+    fn name(&self) -> &str { "pressure" }
+
+    fn section(&self) -> &str { "psi" }
+
+    fn collect(&self) -> (Vec<Pressure>, SectionStatus) {
+        let (mut pressures, status) = partial(get_pressure(&self.services));
+        for pressure in &mut pressures {
+            pressure.level = self.threshold.level(pressure.some.avg10);
+        }
+        (pressures, status)
+    }
+}
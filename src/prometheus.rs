@@ -0,0 +1,266 @@
+// run  := cargo run -- serve
+// dir  := .
+// kid  :=
+
+use std::collections::HashSet;
+use std::fmt::Write as FmtWrite;
+
+use serde_json::Value;
+
+use crate::load_config::Config;
+use crate::system_stats::{CpuFreq, SwapDevice, SystemStats, ZramInfo};
+
+type Samples = Vec<(String, f64)>;
+
+fn escape(value: &str) -> String { value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n") }
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    if pairs.is_empty() {
+        return String::new();
+    }
+    let inner: Vec<String> = pairs.iter().map(|(k, v)| format!("{}=\"{}\"", k, escape(v))).collect();
+    format!("{{{}}}", inner.join(","))
+}
+
+fn gauge(text: &mut String, name: &str, help: &str, samples: Samples) {
+    if samples.is_empty() {
+        return;
+    }
+    writeln!(text, "# HELP {} {}", name, help).unwrap();
+    writeln!(text, "# TYPE {} gauge", name).unwrap();
+    // A repeated series fails the whole scrape, and node_exporter drops the whole textfile, so
+    // only the first of e.g. two plugin rows with the same label is kept.
+    let mut seen = HashSet::new();
+    for (labels, value) in samples {
+        if seen.insert(labels.clone()) {
+            writeln!(text, "{}{} {}", name, labels, value).unwrap();
+        }
+    }
+}
+
+fn single(value: f64) -> Samples { vec![(String::new(), value)] }
+
+pub fn generate_metrics(config: &Config, stats: &SystemStats) -> String {
+    let mut text = String::new();
+    let memory = &stats.memory;
+    if config.memory && stats.collected("memory") {
+        gauge(&mut text, "motd_memory_total_bytes", "Total physical memory.", single(memory.total_mem as f64));
+        gauge(&mut text, "motd_memory_free_bytes", "Free physical memory.", single(memory.free_mem as f64));
+        gauge(&mut text, "motd_memory_available_bytes", "Memory available for new allocations.", single(memory.available_mem as f64));
+        gauge(&mut text, "motd_memory_active_bytes", "Active memory.", single(memory.active_mem as f64));
+        gauge(&mut text, "motd_memory_buffers_bytes", "Memory used by buffers.", single(memory.buffer as f64));
+        gauge(&mut text, "motd_memory_cached_bytes", "Memory used by the page cache.", single(memory.cache as f64));
+        gauge(&mut text, "motd_swap_total_bytes", "Total swap space.", single(memory.total_swap as f64));
+        gauge(&mut text, "motd_swap_free_bytes", "Free swap space.", single(memory.free_swap as f64));
+
+        let (show, details) = (&config.memory_details, &memory.details);
+        if show.dirty {
+            gauge(&mut text, "motd_memory_dirty_bytes", "Memory waiting to be written back to disk.", single(details.dirty as f64));
+            gauge(&mut text, "motd_memory_writeback_bytes", "Memory being written back to disk.", single(details.writeback as f64));
+        }
+        if show.shmem {
+            gauge(&mut text, "motd_memory_shmem_bytes", "Shared memory, including tmpfs.", single(details.shmem as f64));
+        }
+        if show.slab {
+            gauge(&mut text, "motd_memory_slab_reclaimable_bytes", "Kernel slab memory that can be reclaimed.", single(details.sreclaimable as f64));
+            gauge(&mut text, "motd_memory_slab_unreclaimable_bytes", "Kernel slab memory that cannot be reclaimed.", single(details.sunreclaim as f64));
+        }
+        if show.hugepages {
+            gauge(&mut text, "motd_hugepages_total", "Huge pages in the pool.", single(details.hugepages_total as f64));
+            gauge(&mut text, "motd_hugepages_free", "Huge pages not allocated.", single(details.hugepages_free as f64));
+            gauge(&mut text, "motd_hugepage_size_bytes", "Default huge page size.", single(details.hugepage_size as f64));
+        }
+        if show.commit {
+            gauge(&mut text, "motd_memory_committed_bytes", "Memory committed to allocations (Committed_AS).", single(details.committed as f64));
+            gauge(&mut text, "motd_memory_commit_limit_bytes", "Commit limit under strict overcommit (CommitLimit).", single(details.commit_limit as f64));
+        }
+        let zram = |value: fn(&ZramInfo) -> f64| -> Samples { details.zram.iter().map(|z| (labels(&[("device", &z.name)]), value(z))).collect() };
+        gauge(&mut text, "motd_zram_orig_bytes", "Uncompressed size of data stored in zram.", zram(|z| z.orig as f64));
+        gauge(&mut text, "motd_zram_compressed_bytes", "Compressed size of data stored in zram.", zram(|z| z.compressed as f64));
+        gauge(&mut text, "motd_zram_memory_used_bytes", "Memory used by zram, including overhead.", zram(|z| z.mem_used as f64));
+        let swaps = |value: fn(&SwapDevice) -> u64| -> Samples {
+            details.swaps.iter().map(|d| (labels(&[("device", &d.name), ("type", &d.kind)]), value(d) as f64)).collect()
+        };
+        gauge(&mut text, "motd_swap_device_size_bytes", "Size of a swap device.", swaps(|d| d.size));
+        gauge(&mut text, "motd_swap_device_used_bytes", "Used space on a swap device.", swaps(|d| d.used));
+    }
+
+    if config.cpuload && stats.collected("load_avg") {
+        gauge(&mut text, "motd_load1", "1 minute load average.", single(stats.load_avg.one));
+        gauge(&mut text, "motd_load5", "5 minute load average.", single(stats.load_avg.five));
+        gauge(&mut text, "motd_load15", "15 minute load average.", single(stats.load_avg.fifteen));
+        gauge(&mut text, "motd_cpus", "CPUs available to the host or container, after cgroup quotas.", single(stats.load_avg.cpus));
+    }
+
+    if config.cpu.enabled && stats.collected("cpu") {
+        let mut samples = Samples::new();
+        for usage in std::iter::once(&stats.cpu.total).chain(&stats.cpu.cores) {
+            for (mode, value) in [("user", usage.user), ("system", usage.system), ("iowait", usage.iowait), ("steal", usage.steal), ("idle", usage.idle)] {
+                samples.push((labels(&[("cpu", &usage.name), ("mode", mode)]), value));
+            }
+        }
+        gauge(&mut text, "motd_cpu_usage_percent", "Share of CPU time per mode over the sampling interval, cpu=\"cpu\" for all CPUs.", samples);
+    }
+
+    if config.cpuinfo && stats.collected("cpu_info") {
+        let info = &stats.cpu_info;
+        let freqs = |value: fn(&CpuFreq) -> u64| -> Samples {
+            info.freqs.iter().filter(|f| value(f) > 0).map(|f| (labels(&[("cpu", &f.name)]), value(f) as f64 * 1000.0)).collect()
+        };
+        let (governor, epp) = (info.governors.join(","), info.epp.join(","));
+        gauge(&mut text, "motd_cpu_info", "CPU model and frequency scaling policy, always 1.", vec![(labels(&[("model", &info.model), ("governor", &governor), ("epp", &epp)]), 1.0)]);
+        gauge(&mut text, "motd_cpu_sockets", "CPU sockets.", single(info.sockets as f64));
+        gauge(&mut text, "motd_cpu_cores", "Physical CPU cores.", single(info.cores as f64));
+        gauge(&mut text, "motd_cpu_threads", "Online CPU threads.", single(info.threads as f64));
+        gauge(&mut text, "motd_cpu_offline", "CPUs present but offline.", single(info.offline.len() as f64));
+        gauge(&mut text, "motd_cpu_frequency_hertz", "Current CPU frequency.", freqs(|f| f.cur));
+        gauge(&mut text, "motd_cpu_frequency_min_hertz", "Minimum scaling frequency.", freqs(|f| f.min));
+        gauge(&mut text, "motd_cpu_frequency_max_hertz", "Maximum scaling frequency.", freqs(|f| f.max));
+    }
+
+    let mut pressure = Samples::new();
+    for p in &stats.pressure {
+        for (kind, avg) in [("some", Some(&p.some)), ("full", p.full.as_ref())] {
+            for (window, value) in avg.iter().flat_map(|avg| [("10", avg.avg10), ("60", avg.avg60), ("300", avg.avg300)]) {
+                pressure.push((labels(&[("scope", &p.scope), ("resource", &p.resource), ("kind", kind), ("window", window)]), value));
+            }
+        }
+    }
+    gauge(&mut text, "motd_pressure_percent", "Share of time stalled on a resource over a window in seconds.", pressure);
+
+    if config.uptime && stats.collected("uptime") {
+        gauge(&mut text, "motd_uptime_seconds", "System uptime.", single(stats.uptime as f64));
+    }
+
+    gauge(
+        &mut text,
+        "motd_temperature_celsius",
+        "Temperature reported by hwmon sensors.",
+        stats.cpu_temp.iter().map(|t| (labels(&[("chip", &t.chip), ("sensor", &t.name)]), t.temp)).collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_disk_total_bytes",
+        "Total size of the filesystem.",
+        stats.disks.iter().map(|d| (labels(&[("disk", &d.name)]), d.total as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_disk_used_bytes",
+        "Used space on the filesystem.",
+        stats.disks.iter().map(|d| (labels(&[("disk", &d.name)]), d.used as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_subvolume_referenced_bytes",
+        "Bytes referenced by a btrfs subvolume qgroup.",
+        stats
+            .disks
+            .iter()
+            .flat_map(|d| d.subvol.iter().map(move |s| (labels(&[("disk", &d.name), ("subvol", &s.name)]), s.used as f64)))
+            .collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_service_memory_bytes",
+        "Memory used by the systemd unit (MemoryCurrent).",
+        stats.services.iter().filter(|s| !s.failed).map(|s| (labels(&[("service", &s.name)]), s.memory as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_service_state",
+        "Current systemd unit state, always 1.",
+        stats
+            .services
+            .iter()
+            .filter(|s| !s.failed)
+            .map(|s| (labels(&[("service", &s.name), ("state", &s.state), ("substate", &s.substate)]), 1.0))
+            .collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_container_state",
+        "Current docker container state, always 1.",
+        stats.dockers.iter().map(|d| (labels(&[("container", &d.name), ("state", &d.state)]), 1.0)).collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_vm_memory_used_bytes",
+        "Memory used by the libvirt domain.",
+        stats.vms.iter().map(|v| (labels(&[("vm", &v.name)]), v.used_mem as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_vm_memory_total_bytes",
+        "Maximum memory of the libvirt domain.",
+        stats.vms.iter().map(|v| (labels(&[("vm", &v.name)]), v.total_mem as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_vm_cpus",
+        "Virtual CPUs of the libvirt domain.",
+        stats.vms.iter().map(|v| (labels(&[("vm", &v.name)]), v.cpus as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_vm_state",
+        "Current libvirt domain state, always 1.",
+        stats.vms.iter().map(|v| (labels(&[("vm", &v.name), ("state", &v.state)]), 1.0)).collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_gpu_vram_used_bytes",
+        "Used GPU memory.",
+        stats.gpus.iter().enumerate().map(|(i, g)| (labels(&[("gpu", &g.label(i))]), g.used_vram as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_gpu_vram_total_bytes",
+        "Total GPU memory.",
+        stats.gpus.iter().enumerate().map(|(i, g)| (labels(&[("gpu", &g.label(i))]), g.total_vram as f64)).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_gpu_temperature_celsius",
+        "GPU core temperature.",
+        stats.gpus.iter().enumerate().map(|(i, g)| (labels(&[("gpu", &g.label(i)), ("sensor", &g.temp_name)]), g.temp as f64)).collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_plugin_value",
+        "Numeric values reported by plugins.",
+        stats
+            .plugins
+            .iter()
+            .flat_map(|p| {
+                p.rows.iter().filter_map(move |row| match &row.value {
+                    Value::Number(n) => {
+                        n.as_f64().map(|v| (labels(&[("plugin", &p.name), ("label", &row.label), ("unit", &row.unit)]), v))
+                    }
+                    _ => None
+                })
+            })
+            .collect()
+    );
+
+    gauge(
+        &mut text,
+        "motd_section_up",
+        "Whether the section was collected successfully.",
+        stats.status.iter().map(|(section, status)| (labels(&[("section", section)]), if status.is_ok() { 1.0 } else { 0.0 })).collect()
+    );
+    gauge(
+        &mut text,
+        "motd_collection_timestamp_seconds",
+        "Unix time the metrics were collected.",
+        single(stats.timestamp as f64)
+    );
+
+    text
+}
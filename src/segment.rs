@@ -0,0 +1,147 @@
+// run  := cargo run -- segment --template '{load} {mem} {disk}'
+// dir  := .
+// kid  :=
+
+// One-line segments, where `{name}` or `{name:arg}` placeholders in the template become values.
+
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use regex::{Captures, Regex};
+
+use crate::collectors::{spawn, CpuCollector, DiskCollector, LoadCollector, MemoryCollector, SystemdCollector, TempCollector, UptimeCollector};
+use crate::load_config::{Config, SysDisk};
+use crate::system_stats::{wait, SystemStats};
+use crate::thresholds::{percent, Level};
+use crate::utils::s2time;
+
+const FIELDS: [&str; 10] = ["load", "load5", "load15", "cpu", "mem", "swap", "disk", "temp", "failed", "uptime"];
+
+fn placeholders() -> Regex { Regex::new(r"\{(\w+)(?::([^}]*))?\}").unwrap() }
+
+fn find_disk<'a>(disks: &'a [SysDisk], name: Option<&str>) -> Option<&'a SysDisk> {
+    match name {
+        Some(name) => disks.iter().find(|d| d.display == name || d.path == name),
+        None => disks.first()
+    }
+}
+
+// Run only the collectors the template needs, leaving everything else at its default.
+pub fn collect(config: &Config, template: &str) -> SystemStats {
+    let start = Instant::now();
+    let mut sections = BTreeSet::new();
+    let mut disks: Vec<SysDisk> = Vec::new();
+    for caps in placeholders().captures_iter(template) {
+        let section = match &caps[1] {
+            "load" | "load5" | "load15" => "load_avg",
+            "mem" | "swap" => "memory",
+            "cpu" => "cpu",
+            "temp" => "cpu_temp",
+            "failed" => "services",
+            "uptime" => "uptime",
+            "disk" => {
+                if let Some(disk) = find_disk(&config.disk, caps.get(2).map(|m| m.as_str())) {
+                    if !disks.iter().any(|d| d.path == disk.path) {
+                        disks.push(disk.clone());
+                    }
+                }
+                "disks"
+            }
+            _ => continue
+        };
+        sections.insert(section);
+    }
+
+    let mut stats = SystemStats::default();
+    let timeouts = &config.timeouts;
+    let memory = sections.contains("memory").then(|| spawn(MemoryCollector::new(config), timeouts));
+    let load_avg = sections.contains("load_avg").then(|| spawn(LoadCollector::new(config), timeouts));
+    let cpu = sections.contains("cpu").then(|| spawn(CpuCollector::new(config), timeouts));
+    let cpu_temp = sections.contains("cpu_temp").then(|| spawn(TempCollector::new(config), timeouts));
+    let services = sections.contains("services").then(|| spawn(SystemdCollector::new(config), timeouts));
+    let uptime = sections.contains("uptime").then(|| spawn(UptimeCollector, timeouts));
+    let disks = (!disks.is_empty()).then(|| spawn(DiskCollector { disks, threshold: config.thresholds.disk }, timeouts));
+
+    if let Some(pending) = memory {
+        stats.memory = wait(pending, start, &mut stats.status);
+    }
+    if let Some(pending) = load_avg {
+        stats.load_avg = wait(pending, start, &mut stats.status);
+    }
+    if let Some(pending) = cpu {
+        stats.cpu = wait(pending, start, &mut stats.status);
+    }
+    if let Some(pending) = cpu_temp {
+        stats.cpu_temp = wait(pending, start, &mut stats.status);
+    }
+    if let Some(pending) = services {
+        stats.services = wait(pending, start, &mut stats.status);
+    }
+    if let Some(pending) = uptime {
+        stats.uptime = wait(pending, start, &mut stats.status);
+    }
+    if let Some(pending) = disks {
+        stats.disks = wait(pending, start, &mut stats.status);
+    }
+    stats
+}
+
+fn colorize(text: &str, level: Level, color: &str) -> String {
+    match (color, level) {
+        (_, Level::Ok) | ("none", _) => text.to_string(),
+        ("tmux", Level::Warn) => format!("#[fg=yellow]{}#[default]", text),
+        ("tmux", Level::Critical) => format!("#[fg=red,bold]{}#[default]", text),
+        ("zsh", Level::Warn) => format!("%F{{yellow}}{}%f", text),
+        ("zsh", Level::Critical) => format!("%B%F{{red}}{}%f%b", text),
+        // bash only skips bytes between \x01 and \x02 when measuring the prompt width.
+        ("bash", Level::Warn) => format!("\x01\x1b[33m\x02{}\x01\x1b[0m\x02", text),
+        ("bash", Level::Critical) => format!("\x01\x1b[1;31m\x02{}\x01\x1b[0m\x02", text),
+        // Fixed codes, a segment is read from a pipe where the theme would turn colours off.
+        ("ansi", Level::Warn) => format!("\x1b[33m{}\x1b[0m", text),
+        ("ansi", Level::Critical) => format!("\x1b[1;31m{}\x1b[0m", text),
+        _ => text.to_string()
+    }
+}
+
+fn value(config: &Config, stats: &SystemStats, caps: &Captures) -> Option<(String, Level)> {
+    let collected = |section| stats.collected(section);
+    Some(match &caps[1] {
+        "load" if collected("load_avg") => (format!("{:.2}", stats.load_avg.one), stats.load_avg.one_level),
+        "load5" if collected("load_avg") => (format!("{:.2}", stats.load_avg.five), stats.load_avg.five_level),
+        "load15" if collected("load_avg") => (format!("{:.2}", stats.load_avg.fifteen), stats.load_avg.fifteen_level),
+        "cpu" if collected("cpu") => (format!("{:.0}%", stats.cpu.total.busy()), stats.cpu.total.level),
+        "mem" if collected("memory") => {
+            let memory = &stats.memory;
+            (format!("{:.0}%", percent(memory.total_mem - memory.available_mem, memory.total_mem)), memory.level)
+        }
+        "swap" if collected("memory") => {
+            let memory = &stats.memory;
+            (format!("{:.0}%", percent(memory.total_swap - memory.free_swap, memory.total_swap)), memory.swap_level)
+        }
+        "temp" => {
+            let hottest = stats.cpu_temp.iter().max_by(|a, b| a.temp.total_cmp(&b.temp))?;
+            (format!("{:.0}°C", hottest.temp), hottest.level)
+        }
+        "failed" if collected("services") => {
+            let failed = stats.services.iter().filter(|s| s.level == Level::Critical).count();
+            (failed.to_string(), if failed > 0 { Level::Critical } else { Level::Ok })
+        }
+        "uptime" if collected("uptime") => (s2time(stats.uptime), Level::Ok),
+        "disk" => {
+            let wanted = find_disk(&config.disk, caps.get(2).map(|m| m.as_str()))?;
+            let name = if wanted.display != "none" { &wanted.display } else { &wanted.path };
+            let disk = stats.disks.iter().find(|d| &d.name == name)?;
+            (format!("{:.0}%", percent(disk.used, disk.total)), disk.level)
+        }
+        _ => return None
+    })
+}
+
+pub fn generate_segment(config: &Config, stats: &SystemStats, template: &str, color: &str) -> String {
+    let line = placeholders().replace_all(template, |caps: &Captures| match value(config, stats, caps) {
+        Some((text, level)) => colorize(&text, level, color),
+        None if FIELDS.contains(&&caps[1]) => "?".to_string(),
+        None => caps[0].to_string()
+    });
+    format!("{}\n", line)
+}
@@ -0,0 +1,66 @@
+// run  := cargo run -- serve --listen 127.0.0.1:9101
+// dir  := .
+// kid  :=
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use crate::load_config::Config;
+use crate::prometheus;
+use crate::system_stats::SystemStats;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn handle(config: &Config, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Drain the headers; nothing in them changes the response.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+    match (method, path) {
+        ("GET", "/metrics") => {
+            let stats = SystemStats::new(config);
+            respond(&mut stream, "200 OK", CONTENT_TYPE, &prometheus::generate_metrics(config, &stats))
+        }
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html", "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n"),
+        ("GET", _) => respond(&mut stream, "404 Not Found", "text/plain", "Not Found\n"),
+        _ => respond(&mut stream, "405 Method Not Allowed", "text/plain", "Method Not Allowed\n")
+    }
+}
+
+pub fn run(config: &Config, listen: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle(config, stream) {
+                    eprintln!("Request failed: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Connection failed: {}", e)
+        }
+    }
+    Ok(())
+}
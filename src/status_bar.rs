@@ -0,0 +1,231 @@
+// run  := cargo run -- --format waybar
+// dir  := .
+// kid  :=
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::cache;
+use crate::load_config::Config;
+use crate::system_stats::SystemStats;
+use crate::thresholds::{percent, Level};
+use crate::utils::{byte2str, s2time};
+
+struct Chunk {
+    name:       &'static str,
+    instance:   String,
+    text:       String,
+    tooltip:    String,
+    level:      Level,
+    percentage: Option<f64>
+}
+
+fn chunk(name: &'static str, instance: &str, text: String, tooltip: String, level: Level, percentage: Option<f64>) -> Chunk {
+    Chunk { name, instance: instance.to_string(), text, tooltip, level, percentage }
+}
+
+fn chunks(config: &Config, stats: &SystemStats) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    for field in &config.statusbar.fields {
+        match field.as_str() {
+            "memory" if stats.collected("memory") => {
+                let memory = &stats.memory;
+                let used = memory.total_mem - memory.available_mem;
+                let pct = percent(used, memory.total_mem);
+                chunks.push(chunk(
+                    "memory",
+                    "",
+                    format!("MEM {:.0}%", pct),
+                    format!("Memory {} / {}", byte2str(used, true), byte2str(memory.total_mem, true)),
+                    memory.level,
+                    Some(pct)
+                ));
+            }
+            "swap" if stats.collected("memory") && stats.memory.total_swap > 0 => {
+                let memory = &stats.memory;
+                let used = memory.total_swap - memory.free_swap;
+                let pct = percent(used, memory.total_swap);
+                chunks.push(chunk(
+                    "swap",
+                    "",
+                    format!("SWP {:.0}%", pct),
+                    format!("Swap {} / {}", byte2str(used, true), byte2str(memory.total_swap, true)),
+                    memory.swap_level,
+                    Some(pct)
+                ));
+            }
+            "load" if stats.collected("load_avg") => {
+                let load = &stats.load_avg;
+                chunks.push(chunk(
+                    "load",
+                    "",
+                    format!("LOAD {:.2}", load.one),
+                    format!("Load {:.2} {:.2} {:.2}, CPUs {}", load.one, load.five, load.fifteen, load.cpus),
+                    load.one_level,
+                    None
+                ));
+            }
+            "cpu" if stats.collected("cpu") => {
+                let total = &stats.cpu.total;
+                chunks.push(chunk(
+                    "cpu",
+                    "",
+                    format!("CPU {:.0}%", total.busy()),
+                    format!("CPU user {:.1}% system {:.1}% iowait {:.1}% steal {:.1}%", total.user, total.system, total.iowait, total.steal),
+                    total.level,
+                    Some(total.busy())
+                ));
+            }
+            "cputemp" => {
+                if let Some(hottest) = stats.cpu_temp.iter().max_by(|a, b| a.temp.total_cmp(&b.temp)) {
+                    let tooltip: Vec<String> = stats.cpu_temp.iter().map(|t| format!("{} {:.1}°C", t.name, t.temp)).collect();
+                    chunks.push(chunk("cputemp", &hottest.name, format!("{:.0}°C", hottest.temp), tooltip.join("\n"), hottest.level, None));
+                }
+            }
+            "disk" => {
+                for disk in &stats.disks {
+                    let pct = percent(disk.used, disk.total);
+                    chunks.push(chunk(
+                        "disk",
+                        &disk.name,
+                        format!("{} {:.0}%", disk.name, pct),
+                        format!("{} {} / {}", disk.name, byte2str(disk.used, false), byte2str(disk.total, false)),
+                        disk.level,
+                        Some(pct)
+                    ));
+                }
+            }
+            "services" if !stats.services.is_empty() => {
+                let level = stats.services.iter().map(|s| s.level).max().unwrap_or_default();
+                let healthy = stats.services.iter().filter(|s| s.level == Level::Ok).count();
+                let tooltip: Vec<String> = stats.services.iter().map(|s| format!("{} {} ({})", s.name, s.state, s.substate)).collect();
+                chunks.push(chunk(
+                    "services",
+                    "",
+                    format!("SVC {}/{}", healthy, stats.services.len()),
+                    tooltip.join("\n"),
+                    level,
+                    None
+                ));
+            }
+            "docker" if !stats.dockers.is_empty() => {
+                let running = stats.dockers.iter().filter(|d| d.state == "running").count();
+                let tooltip: Vec<String> = stats.dockers.iter().map(|d| format!("{} {}", d.name, d.status)).collect();
+                chunks.push(chunk(
+                    "docker",
+                    "",
+                    format!("CTR {}/{}", running, stats.dockers.len()),
+                    tooltip.join("\n"),
+                    Level::Ok,
+                    None
+                ));
+            }
+            "vm" if !stats.vms.is_empty() => {
+                let level = stats.vms.iter().map(|v| v.level).max().unwrap_or_default();
+                let running = stats.vms.iter().filter(|v| v.state == "running").count();
+                let tooltip: Vec<String> = stats.vms.iter().map(|v| format!("{} {}", v.name, v.state)).collect();
+                chunks.push(chunk("vm", "", format!("VM {}/{}", running, stats.vms.len()), tooltip.join("\n"), level, None));
+            }
+            "gpu" => {
+                for gpu in &stats.gpus {
+                    let pct = percent(gpu.used_vram, gpu.total_vram);
+                    chunks.push(chunk(
+                        "gpu",
+                        &gpu.mem_name,
+                        format!("GPU {:.0}% {}°C", pct, gpu.temp),
+                        format!("{} {} / {}\n{} {}°C", gpu.mem_name, byte2str(gpu.used_vram, true), byte2str(gpu.total_vram, true), gpu.temp_name, gpu.temp),
+                        gpu.temp_level,
+                        Some(pct)
+                    ));
+                }
+            }
+            "uptime" if stats.collected("uptime") => {
+                let uptime = s2time(stats.uptime);
+                chunks.push(chunk("uptime", "", format!("UP {}", uptime), format!("Uptime {}", uptime), Level::Ok, None));
+            }
+            _ => {}
+        }
+    }
+    chunks
+}
+
+fn class(level: Level) -> &'static str {
+    match level {
+        Level::Ok => "ok",
+        Level::Warn => "warn",
+        Level::Critical => "critical"
+    }
+}
+
+fn color(config: &Config, level: Level) -> Option<&str> {
+    match level {
+        Level::Ok => None,
+        Level::Warn => Some(&config.statusbar.warn_color),
+        Level::Critical => Some(&config.statusbar.critical_color)
+    }
+}
+
+// One JSON object per run, as expected by a waybar `custom` module with `return-type: json`. The
+// percentage is that of the fullest resource, so `format-icons` track whatever is closest to full.
+pub fn generate_waybar(config: &Config, stats: &SystemStats) -> String {
+    let chunks = chunks(config, stats);
+    let text: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+    let tooltip: Vec<&str> = chunks.iter().map(|c| c.tooltip.as_str()).collect();
+    let level = chunks.iter().map(|c| c.level).max().unwrap_or_default();
+    let percentage = chunks.iter().filter_map(|c| c.percentage).fold(0.0, f64::max);
+    let output = json!({
+        "text": text.join(&config.statusbar.separator),
+        "tooltip": tooltip.join("\n"),
+        "class": class(level),
+        "percentage": percentage.round() as u64
+    });
+    format!("{}\n", output)
+}
+
+pub fn generate_polybar(config: &Config, stats: &SystemStats) -> String {
+    let text: Vec<String> = chunks(config, stats)
+        .into_iter()
+        .map(|c| match color(config, c.level) {
+            Some(color) => format!("%{{F{}}}{}%{{F-}}", color, c.text),
+            None => c.text
+        })
+        .collect();
+    format!("{}\n", text.join(&config.statusbar.separator))
+}
+
+fn i3bar_blocks(config: &Config, stats: &SystemStats) -> Value {
+    let blocks: Vec<Value> = chunks(config, stats)
+        .into_iter()
+        .map(|c| {
+            let mut block = json!({
+                "name": c.name,
+                "instance": c.instance,
+                "full_text": c.text,
+                "urgent": c.level == Level::Critical
+            });
+            if let Some(color) = color(config, c.level) {
+                block["color"] = json!(color);
+            }
+            block
+        })
+        .collect();
+    Value::Array(blocks)
+}
+
+// i3bar reads an endless JSON array from its status_command, so this keeps running and appends
+// one set of blocks every `statusbar.interval` seconds.
+pub fn run_i3bar(config: &Config, live: bool) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", json!({ "version": 1 }))?;
+    writeln!(stdout, "[")?;
+    loop {
+        let stats = if live { None } else { cache::read_cache(config) }
+            .unwrap_or_else(|| SystemStats::new(config));
+        writeln!(stdout, "{},", i3bar_blocks(config, &stats))?;
+        stdout.flush()?;
+        thread::sleep(Duration::from_secs(config.statusbar.interval));
+    }
+}
@@ -4,38 +4,53 @@
 
 extern crate libc;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::CString;
 use std::fs::File;
 use std::io::BufRead;
 use std::os::raw::c_char;
 use std::path::Path;
 use std::process::Command;
-use std::{fs, io, mem, str};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fs, io, mem, str, thread};
 
 use libc::{statvfs, statvfs as statvfs_t};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::load_config::{Config, SysDisk, SysDocker, SysGpu, SysService, SysVm};
+use crate::collectors::{
+    spawn, Collector, CpuCollector, CpuInfoCollector, DiskCollector, DockerCollector, LastLoginCollector, LibvirtCollector,
+    LoadCollector, MemoryCollector, NvidiaCollector, Pending, SystemdCollector, TempCollector, UptimeCollector
+};
+use crate::error::{run_command, CollectError, SectionStatus};
+use crate::identity::{IdentityCollector, IdentityInfo};
+use crate::plugin::{PluginCollector, PluginSection};
+use crate::pressure::{Pressure, PressureCollector};
+use crate::load_config::{Config, StateThreshold, SysDisk, SysDocker, SysGpu, SysService, SysVm, Threshold};
+use crate::thresholds::{percent, Level};
 use crate::utils::str2byte;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Service {
     pub name:     String,
     pub memory:   u64,
     pub state:    String,
-    pub substate: String
+    pub substate: String,
+    pub level:    Level,
+    // Placeholder for a unit that could not be queried, shown in the MOTD but not exported.
+    #[serde(default)]
+    pub failed:   bool
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Docker {
     pub name:   String,
     pub state:  String,
     pub status: String
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct MemInfo {
     pub total_mem:     u64,
     pub free_mem:      u64,
@@ -44,84 +59,219 @@ pub struct MemInfo {
     pub cache:         u64,
     pub available_mem: u64,
     pub total_swap:    u64,
-    pub free_swap:     u64
+    pub free_swap:     u64,
+    pub level:         Level,
+    pub swap_level:    Level,
+    #[serde(default)]
+    pub details:       MemDetails
 }
 
-#[derive(Debug)]
+// The [memory_details] part of /proc/meminfo, in bytes except for the hugepage counts.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MemDetails {
+    pub dirty:           u64,
+    pub writeback:       u64,
+    pub shmem:           u64,
+    pub sreclaimable:    u64,
+    pub sunreclaim:      u64,
+    pub hugepages_total: u64,
+    pub hugepages_free:  u64,
+    pub hugepage_size:   u64,
+    pub committed:       u64,
+    pub commit_limit:    u64,
+    pub zram:            Vec<ZramInfo>,
+    pub swaps:           Vec<SwapDevice>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ZramInfo {
+    pub name:       String,
+    pub orig:       u64,
+    pub compressed: u64,
+    pub mem_used:   u64
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SwapDevice {
+    pub name:     String,
+    pub kind:     String,
+    pub size:     u64,
+    pub used:     u64,
+    pub priority: i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct LoadAvgInfo {
-    pub one:     f64,
-    pub five:    f64,
-    pub fifteen: f64
+    pub one:           f64,
+    pub five:          f64,
+    pub fifteen:       f64,
+    // CPUs this process may run on, fractional under a cgroup CPU quota.
+    #[serde(default)]
+    pub cpus:          f64,
+    pub one_level:     Level,
+    pub five_level:    Level,
+    pub fifteen_level: Level
 }
 
-#[derive(Debug)]
+// Share of time in each state between two /proc/stat samples, in percent. Nice time counts as
+// user, irq and softirq as system.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CpuUsage {
+    pub name:   String,
+    pub user:   f64,
+    pub system: f64,
+    pub iowait: f64,
+    pub steal:  f64,
+    pub idle:   f64,
+    pub level:  Level
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CpuStats {
+    pub total: CpuUsage,
+    pub cores: Vec<CpuUsage>
+}
+
+// Frequencies in kHz, as cpufreq reports them.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CpuFreq {
+    pub name: String,
+    pub cur:  u64,
+    pub min:  u64,
+    pub max:  u64
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CpuInfo {
+    pub model:          String,
+    pub sockets:        usize,
+    pub cores:          usize,
+    pub threads:        usize,
+    pub offline:        Vec<usize>,
+    pub freqs:          Vec<CpuFreq>,
+    // Distinct values over all cores, usually just one.
+    pub governors:      Vec<String>,
+    pub epp:            Vec<String>,
+    pub governor_level: Level
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct LoginInfo {
     pub user: String,
     pub host: String
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TempInfo {
+    pub name:  String,
+    // hwmon device and driver, e.g. "hwmon2:coretemp", as labels repeat across packages.
+    #[serde(default)]
+    pub chip:  String,
+    pub temp:  f64,
+    pub level: Level
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubvolInfo {
+    pub name: String,
+    pub used: u64
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DiskInfo {
     pub name:   String,
     pub total:  u64,
     pub used:   u64,
-    pub subvol: Vec<(String, u64)>
+    pub subvol: Vec<SubvolInfo>,
+    pub level:  Level
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct VmInfo {
     pub name:      String,
     pub state:     String,
     pub cpus:      u64,
     pub used_mem:  u64,
     pub total_mem: u64,
-    pub autostart: String
+    pub autostart: String,
+    pub level:     Level
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GpuInfo {
     pub mem_name:   String,
     pub temp_name:  String,
     pub temp:       u64,
     pub used_vram:  u64,
-    pub total_vram: u64
+    pub total_vram: u64,
+    pub temp_level: Level
+}
+
+impl GpuInfo {
+    // Display names default to the same value for every GPU, so exporters key them by position too.
+    pub fn label(&self, index: usize) -> String { format!("{}:{}", index, self.mem_name) }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct SystemStats {
+    #[serde(default)]
+    pub identity:   IdentityInfo,
     pub memory:     MemInfo,
     pub load_avg:   LoadAvgInfo,
-    pub cpu_temp:   Vec<(String, u64)>,
+    #[serde(default)]
+    pub cpu:        CpuStats,
+    #[serde(default)]
+    pub cpu_info:   CpuInfo,
+    #[serde(default)]
+    pub pressure:   Vec<Pressure>,
+    pub cpu_temp:   Vec<TempInfo>,
     pub uptime:     u64,
     pub last_login: LoginInfo,
     pub disks:      Vec<DiskInfo>,
     pub services:   Vec<Service>,
     pub dockers:    Vec<Docker>,
     pub vms:        Vec<VmInfo>,
-    pub gpus:       Vec<GpuInfo>
+    pub gpus:       Vec<GpuInfo>,
+    pub plugins:    Vec<PluginSection>,
+    pub status:     BTreeMap<String, SectionStatus>,
+    pub timestamp:  u64,
+    // `Config::identity` of the config the snapshot was collected with.
+    #[serde(default)]
+    pub config:     String,
+    #[serde(skip)]
+    pub cached:     bool
 }
 
-pub fn get_memory() -> io::Result<MemInfo> {
+pub fn get_memory() -> Result<MemInfo, CollectError> {
     let path = Path::new("/proc/meminfo");
-    let file = File::open(path)?;
+    let file = File::open(path).map_err(|e| CollectError::Unavailable(format!("{}: {}", path.display(), e)))?;
     let reader = io::BufReader::new(file);
 
-    let mut mem_info = MemInfo {
-        total_mem:     0,
-        free_mem:      0,
-        active_mem:    0,
-        buffer:        0,
-        cache:         0,
-        available_mem: 0,
-        total_swap:    0,
-        free_swap:     0
-    };
-
-    let keys_of_interest: HashSet<&str> =
-        ["MemTotal:", "MemFree:", "MemAvailable:", "Buffers:", "Active:", "Cached:", "SwapTotal:", "SwapFree:"]
-            .iter()
-            .cloned()
-            .collect();
+    let mut mem_info = MemInfo::default();
+
+    let keys_of_interest: HashSet<&str> = [
+        "MemTotal:",
+        "MemFree:",
+        "MemAvailable:",
+        "Buffers:",
+        "Active:",
+        "Cached:",
+        "SwapTotal:",
+        "SwapFree:",
+        "Dirty:",
+        "Writeback:",
+        "Shmem:",
+        "SReclaimable:",
+        "SUnreclaim:",
+        "HugePages_Total:",
+        "HugePages_Free:",
+        "Hugepagesize:",
+        "Committed_AS:",
+        "CommitLimit:"
+    ]
+    .iter()
+    .cloned()
+    .collect();
 
     for line in reader.lines() {
         let line = line?;
@@ -130,7 +280,11 @@ pub fn get_memory() -> io::Result<MemInfo> {
         if let Some(key) = parts.first() {
             if keys_of_interest.contains(key) {
                 if let Some(value_str) = parts.get(1) {
-                    let value = value_str.parse::<u64>().unwrap() * 1024;
+                    let value = value_str
+                        .parse::<u64>()
+                        .map_err(|e| CollectError::Failed(format!("invalid /proc/meminfo value for {} {}", key, e)))?;
+                    // Everything but the hugepage counts is in kB.
+                    let value = if parts.get(2) == Some(&"kB") { value * 1024 } else { value };
                     match *key {
                         "MemTotal:" => mem_info.total_mem = value,
                         "MemFree:" => mem_info.free_mem = value,
@@ -140,6 +294,16 @@ pub fn get_memory() -> io::Result<MemInfo> {
                         "Cached:" => mem_info.cache = value,
                         "SwapTotal:" => mem_info.total_swap = value,
                         "SwapFree:" => mem_info.free_swap = value,
+                        "Dirty:" => mem_info.details.dirty = value,
+                        "Writeback:" => mem_info.details.writeback = value,
+                        "Shmem:" => mem_info.details.shmem = value,
+                        "SReclaimable:" => mem_info.details.sreclaimable = value,
+                        "SUnreclaim:" => mem_info.details.sunreclaim = value,
+                        "HugePages_Total:" => mem_info.details.hugepages_total = value,
+                        "HugePages_Free:" => mem_info.details.hugepages_free = value,
+                        "Hugepagesize:" => mem_info.details.hugepage_size = value,
+                        "Committed_AS:" => mem_info.details.committed = value,
+                        "CommitLimit:" => mem_info.details.commit_limit = value,
                         _ => ()
                     }
                 }
@@ -150,46 +314,274 @@ pub fn get_memory() -> io::Result<MemInfo> {
     Ok(mem_info)
 }
 
-pub fn get_load() -> LoadAvgInfo {
+impl ZramInfo {
+    // How many times smaller the data got, 0 while the device is empty.
+    pub fn ratio(&self) -> f64 { if self.compressed > 0 { self.orig as f64 / self.compressed as f64 } else { 0.0 } }
+}
+
+// mm_stat starts with orig_data_size, compr_data_size and mem_used_total, all in bytes.
+pub fn get_zram() -> Vec<ZramInfo> {
+    let mut devices: Vec<ZramInfo> = fs::read_dir("/sys/block")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with("zram") {
+                return None;
+            }
+            let stat = fs::read_to_string(entry.path().join("mm_stat")).ok()?;
+            let fields: Vec<u64> = stat.split_whitespace().take(3).filter_map(|v| v.parse().ok()).collect();
+            match fields.as_slice() {
+                [orig, compressed, mem_used] => Some(ZramInfo { name, orig: *orig, compressed: *compressed, mem_used: *mem_used }),
+                _ => None
+            }
+        })
+        .collect();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+// /proc/swaps lists sizes in kB and escapes spaces in file names as \040.
+pub fn get_swaps() -> Vec<SwapDevice> {
+    let content = fs::read_to_string("/proc/swaps").unwrap_or_default();
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, kind, size, used, priority] = fields.as_slice() else {
+                return None;
+            };
+            Some(SwapDevice {
+                name:     name.replace("\\040", " "),
+                kind:     kind.to_string(),
+                size:     size.parse::<u64>().ok()? * 1024,
+                used:     used.parse::<u64>().ok()? * 1024,
+                priority: priority.parse().unwrap_or(0)
+            })
+        })
+        .collect()
+}
+
+impl LoadAvgInfo {
+    // Load per CPU in percent, 100% being every CPU busy.
+    pub fn per_cpu(&self, load: f64) -> f64 { if self.cpus > 0.0 { load * 100.0 / self.cpus } else { 0.0 } }
+}
+
+// CPUs in a sysfs list such as "0-3,8,10-11".
+fn cpu_list(list: &str) -> Vec<usize> {
+    list.trim()
+        .split(',')
+        .filter_map(|range| match range.split_once('-') {
+            Some((first, last)) => Some((first.parse().ok()?..=last.parse().ok()?).collect::<Vec<_>>()),
+            None => range.parse().ok().map(|cpu| vec![cpu])
+        })
+        .flatten()
+        .collect()
+}
+
+fn affinity_cpus() -> Option<usize> {
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    if unsafe { libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) } != 0 {
+        return None;
+    }
+    let count = unsafe { libc::CPU_COUNT(&set) } as usize;
+    (count > 0).then_some(count)
+}
+
+// The cgroup CPU quota as a number of CPUs, from cgroup v2 cpu.max or v1 cfs_quota_us.
+fn cgroup_cpu_quota() -> Option<f64> {
+    let cgroup = fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    let v2_path = cgroup.lines().find_map(|line| line.strip_prefix("0::")).unwrap_or("/");
+    for dir in [format!("/sys/fs/cgroup{}", v2_path.trim_end_matches('/')), "/sys/fs/cgroup".to_string()] {
+        if let Ok(max) = fs::read_to_string(format!("{}/cpu.max", dir)) {
+            let mut fields = max.split_whitespace();
+            let quota = fields.next()?.parse::<f64>().ok()?;
+            let period = fields.next()?.parse::<f64>().ok()?;
+            return (period > 0.0).then(|| quota / period);
+        }
+    }
+    let read = |name: &str| fs::read_to_string(format!("/sys/fs/cgroup/cpu,cpuacct/{}", name))
+        .or_else(|_| fs::read_to_string(format!("/sys/fs/cgroup/cpu/{}", name)))
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok());
+    let (quota, period) = (read("cpu.cfs_quota_us")?, read("cpu.cfs_period_us")?);
+    (quota > 0.0 && period > 0.0).then(|| quota / period)
+}
+
+pub fn get_cpu_count() -> f64 {
+    let online = fs::read_to_string("/sys/devices/system/cpu/online").map(|list| cpu_list(&list).len()).unwrap_or(0);
+    let cpus = match (affinity_cpus(), online) {
+        (Some(affinity), 0) => affinity,
+        (Some(affinity), online) => affinity.min(online),
+        (None, 0) => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        (None, online) => online
+    } as f64;
+    match cgroup_cpu_quota() {
+        Some(quota) if quota < cpus => quota,
+        _ => cpus
+    }
+}
+
+impl CpuUsage {
+    pub fn busy(&self) -> f64 { self.user + self.system + self.steal }
+}
+
+// Jiffies per cpu line of /proc/stat: user, nice, system, idle, iowait, irq, softirq, steal.
+fn read_cpu_times() -> Result<Vec<(String, [u64; 8])>, CollectError> {
+    let content = fs::read_to_string("/proc/stat")?;
+    Ok(content
+        .lines()
+        .filter(|line| line.starts_with("cpu"))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let mut times = [0; 8];
+            for (time, field) in times.iter_mut().zip(fields) {
+                *time = field.parse().ok()?;
+            }
+            Some((name, times))
+        })
+        .collect())
+}
+
+fn cpu_usage(name: &str, before: &[u64; 8], after: &[u64; 8]) -> CpuUsage {
+    let delta: Vec<f64> = before.iter().zip(after).map(|(b, a)| a.saturating_sub(*b) as f64).collect();
+    let total: f64 = delta.iter().sum();
+    let share = |jiffies: f64| if total > 0.0 { jiffies * 100.0 / total } else { 0.0 };
+    CpuUsage {
+        name:   name.to_string(),
+        user:   share(delta[0] + delta[1]),
+        system: share(delta[2] + delta[5] + delta[6]),
+        iowait: share(delta[4]),
+        steal:  share(delta[7]),
+        idle:   if total > 0.0 { share(delta[3]) } else { 100.0 },
+        level:  Level::Ok
+    }
+}
+
+pub fn get_cpu_usage(interval: Duration) -> Result<CpuStats, CollectError> {
+    let before = read_cpu_times()?;
+    thread::sleep(interval);
+    let after = read_cpu_times()?;
+    let mut stats = CpuStats::default();
+    // Cores going offline between the samples are left out.
+    for (name, times) in &after {
+        let Some((_, previous)) = before.iter().find(|(n, _)| n == name) else {
+            continue;
+        };
+        let usage = cpu_usage(name, previous, times);
+        if name == "cpu" {
+            stats.total = usage;
+        }
+        else {
+            stats.cores.push(usage);
+        }
+    }
+    if stats.total.name.is_empty() {
+        return Err(CollectError::Failed("no cpu line in /proc/stat".to_string()));
+    }
+    Ok(stats)
+}
+
+pub fn get_load() -> Result<LoadAvgInfo, CollectError> {
     let mut loadavg = [0.0_f64; 3];
     let result = unsafe { libc::getloadavg(loadavg.as_mut_ptr(), 3) };
 
     if result != -1 {
-        LoadAvgInfo { one: loadavg[0], five: loadavg[1], fifteen: loadavg[2] }
+        Ok(LoadAvgInfo { one: loadavg[0], five: loadavg[1], fifteen: loadavg[2], cpus: get_cpu_count(), ..Default::default() })
     }
     else {
-        eprintln!("Failed to get load average");
-        std::process::exit(1);
+        Err(CollectError::Failed("getloadavg failed".to_string()))
     }
 }
 
-fn get_cpu_temp(cpu_restr: &str) -> io::Result<Vec<(String, u64)>> {
+fn cpu_model(cpuinfo: &str) -> Option<String> {
+    // x86 has "model name", arm "Model" or "Hardware", powerpc "cpu".
+    ["model name", "Model", "Hardware", "cpu"].iter().find_map(|key| {
+        cpuinfo.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            (name.trim() == *key && !value.trim().is_empty()).then(|| value.trim().to_string())
+        })
+    })
+}
+
+pub fn get_cpu_info() -> Result<CpuInfo, CollectError> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo")?;
+    let base = Path::new("/sys/devices/system/cpu");
+    let read = |path: &Path| fs::read_to_string(path).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let online = read(&base.join("online")).map(|list| cpu_list(&list)).unwrap_or_default();
+    let mut info = CpuInfo {
+        model: cpu_model(&cpuinfo).unwrap_or_else(|| "unknown".to_string()),
+        offline: read(&base.join("offline")).map(|list| cpu_list(&list)).unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let mut packages = HashSet::new();
+    let mut cores = HashSet::new();
+    for cpu in &online {
+        let dir = base.join(format!("cpu{}", cpu));
+        let package = read(&dir.join("topology/physical_package_id")).unwrap_or_default();
+        let core = read(&dir.join("topology/core_id")).unwrap_or_else(|| cpu.to_string());
+        cores.insert((package.clone(), core));
+        packages.insert(package);
+
+        let freq = |name: &str| read(&dir.join("cpufreq").join(name)).and_then(|v| v.parse().ok());
+        if let Some(cur) = freq("scaling_cur_freq") {
+            info.freqs.push(CpuFreq { name: format!("cpu{}", cpu), cur, min: freq("scaling_min_freq").unwrap_or(0), max: freq("scaling_max_freq").unwrap_or(0) });
+        }
+        for (values, name) in [(&mut info.governors, "scaling_governor"), (&mut info.epp, "energy_performance_preference")] {
+            if let Some(value) = read(&dir.join("cpufreq").join(name)) {
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+            }
+        }
+    }
+    info.governors.sort();
+    info.epp.sort();
+    (info.sockets, info.cores, info.threads) = (packages.len(), cores.len(), online.len());
+    if info.threads == 0 {
+        // Without sysfs, count processor entries instead.
+        info.threads = cpuinfo.lines().filter(|line| line.starts_with("processor")).count();
+        (info.sockets, info.cores) = (1, info.threads);
+    }
+    Ok(info)
+}
+
+pub fn get_cpu_temp(cpu_restr: &str) -> Result<Vec<TempInfo>, CollectError> {
     let mut temperatures = Vec::new();
-    let hwmon_paths = fs::read_dir("/sys/class/hwmon/")?;
+    let hwmon_paths =
+        fs::read_dir("/sys/class/hwmon/").map_err(|e| CollectError::Unavailable(format!("/sys/class/hwmon: {}", e)))?;
 
-    let regex = Regex::new(cpu_restr).expect("Invalid regular expression");
+    let regex = Regex::new(cpu_restr).map_err(|e| CollectError::Failed(format!("invalid cputemp pattern: {}", e)))?;
 
     for hwmon_path in hwmon_paths {
         let hwmon_path = hwmon_path?.path();
         if let Ok(name) = fs::read_to_string(hwmon_path.join("name")) {
+            let chip = format!("{}:{}", hwmon_path.file_name().unwrap_or_default().to_string_lossy(), name.trim());
             if regex.is_match(name.trim()) {
                 for entry in fs::read_dir(&hwmon_path)? {
-                    let entry = entry?;
-                    let filename = entry.file_name().into_string().unwrap();
+                    let filename = entry?.file_name().to_string_lossy().to_string();
                     if filename.starts_with("temp") && filename.ends_with("_input") {
                         let temp_file = hwmon_path.join(&filename);
-                        let temp = fs::read_to_string(&temp_file)?.trim().parse::<u64>().unwrap();
+                        let Ok(temp) = fs::read_to_string(&temp_file)?.trim().parse::<u64>()
+                        else {
+                            continue;
+                        };
 
                         let label_filename = filename.replace("_input", "_label");
                         let label_file = hwmon_path.join(&label_filename);
+                        // Unlabelled sensors go by their input, e.g. "temp2", to tell them apart.
                         let label = if label_file.exists() {
                             fs::read_to_string(&label_file)?.trim().to_string()
                         }
                         else {
-                            "Unknown".to_string()
+                            filename.trim_end_matches("_input").to_string()
                         };
 
-                        temperatures.push((label, temp));
+                        temperatures.push(TempInfo { name: label, chip: chip.clone(), temp: temp as f64 / 1000.0, level: Level::Ok });
                     }
                 }
             }
@@ -198,124 +590,96 @@ fn get_cpu_temp(cpu_restr: &str) -> io::Result<Vec<(String, u64)>> {
     Ok(temperatures)
 }
 
-fn get_uptime() -> u64 {
+pub fn get_uptime() -> Result<u64, CollectError> {
     unsafe {
         let mut info: libc::sysinfo = mem::zeroed();
         if libc::sysinfo(&mut info) == 0 {
-            info.uptime as u64
+            Ok(info.uptime as u64)
         }
         else {
-            0
+            Err(io::Error::last_os_error().into())
         }
     }
 }
 
-pub fn get_last_login() -> LoginInfo {
-    let output = Command::new("last")
-        .arg("-T")
-        .arg("-w")
-        .arg("-i")
-        .arg("-n")
-        .arg("2")
-        .output()
-        .expect("Failed to execute command");
-
-    if output.status.success() {
-        let output_str = str::from_utf8(&output.stdout).unwrap_or("Invalid UTF-8");
-        let lines: Vec<&str> = output_str.lines().collect();
-        if lines.len() > 1 {
-            let last_login_line = lines[1];
-            let parts: Vec<&str> = last_login_line.split('\t').collect();
-            if parts.len() >= 3 {
-                let user = parts[0].trim().to_string();
-                let host = parts[2].trim().to_string();
-                return LoginInfo { user, host };
-            }
+pub fn get_last_login() -> Result<LoginInfo, CollectError> {
+    let output_str = run_command(Command::new("last").arg("-T").arg("-w").arg("-i").arg("-n").arg("2"))?;
+    let lines: Vec<&str> = output_str.lines().collect();
+    if lines.len() > 1 {
+        let last_login_line = lines[1];
+        let parts: Vec<&str> = last_login_line.split('\t').collect();
+        if parts.len() >= 3 {
+            let user = parts[0].trim().to_string();
+            let host = parts[2].trim().to_string();
+            return Ok(LoginInfo { user, host });
         }
     }
-    else {
-        let error_str = str::from_utf8(&output.stderr).unwrap_or("Invalid UTF-8");
-        eprintln!("Error: {}", error_str);
-    }
-    LoginInfo { user: "".to_string(), host: "".to_string() }
+    Ok(LoginInfo::default())
 }
 
-pub fn get_service(service: &SysService) -> Service {
+pub fn get_service(service: &SysService, threshold: &StateThreshold) -> Result<Service, CollectError> {
     let service_name = if service.display != "none" { &service.display } else { &service.name };
-    let output = Command::new("systemctl")
-        .arg("show")
-        .arg(&service.name)
-        .arg("--property=ActiveState,SubState,MemoryCurrent")
-        .output()
-        .expect("Failed to execute command");
-    if output.status.success() {
-        let output_str = str::from_utf8(&output.stdout).unwrap_or("Invalid UTF-8");
-        let mut memory_current: u64 = 0;
-        let mut active_state = String::new();
-        let mut sub_state = String::new();
-        for line in output_str.lines() {
-            let parts: Vec<&str> = line.split('=').collect();
-            if parts.len() == 2 {
-                match parts[0] {
-                    "MemoryCurrent" => memory_current = parts[1].parse().unwrap_or(0),
-                    "ActiveState" => active_state = parts[1].to_string(),
-                    "SubState" => sub_state = parts[1].to_string(),
-                    _ => {}
-                }
+    let output_str =
+        run_command(Command::new("systemctl").arg("show").arg(&service.name).arg("--property=ActiveState,SubState,MemoryCurrent"))?;
+    let mut memory_current: u64 = 0;
+    let mut active_state = String::new();
+    let mut sub_state = String::new();
+    for line in output_str.lines() {
+        let parts: Vec<&str> = line.split('=').collect();
+        if parts.len() == 2 {
+            match parts[0] {
+                "MemoryCurrent" => memory_current = parts[1].parse().unwrap_or(0),
+                "ActiveState" => active_state = parts[1].to_string(),
+                "SubState" => sub_state = parts[1].to_string(),
+                _ => {}
             }
         }
-        return Service {
-            name:     service_name.to_string(),
-            memory:   memory_current,
-            state:    active_state,
-            substate: sub_state
-        };
-    }
-    else {
-        let error_str = str::from_utf8(&output.stderr).unwrap_or("Invalid UTF-8");
-        eprintln!("Error: {}", error_str);
     }
-    Service {
+    Ok(Service {
         name:     service_name.to_string(),
-        memory:   0,
-        state:    "unknown".to_string(),
-        substate: "unknown".to_string()
-    }
+        memory:   memory_current,
+        level:    threshold.level(&active_state),
+        state:    active_state,
+        substate: sub_state,
+        failed:   false
+    })
 }
 
-pub fn get_service_all(systemctl: &Vec<SysService>) -> Vec<Service> {
+pub fn get_service_all(systemctl: &Vec<SysService>, threshold: &StateThreshold) -> (Vec<Service>, Option<CollectError>) {
     let mut services: Vec<Service> = Vec::new();
+    let mut error = None;
     for service in systemctl {
-        let service_info = get_service(service);
-        services.push(service_info);
+        match get_service(service, threshold) {
+            Ok(service_info) => services.push(service_info),
+            Err(e @ CollectError::Unavailable(_)) => return (Vec::new(), Some(e)),
+            Err(e) => {
+                services.push(Service {
+                    name:     if service.display != "none" { &service.display } else { &service.name }.to_string(),
+                    memory:   0,
+                    state:    "unknown".to_string(),
+                    substate: "unknown".to_string(),
+                    level:    threshold.level("unknown"),
+                    failed:   true
+                });
+                error.get_or_insert(e);
+            }
+        }
     }
-    services
+    (services, error)
 }
 
-pub fn get_docker(containers: &Vec<SysDocker>) -> Vec<Docker> {
+pub fn get_docker(containers: &Vec<SysDocker>) -> Result<Vec<Docker>, CollectError> {
     let mut container_map: HashMap<String, (String, String)> = HashMap::new();
-    let output = Command::new("docker")
-        .arg("ps")
-        .arg("--format")
-        .arg("{{.Names}}:{{.State}}:{{.Status}}")
-        .output()
-        .expect("Failed to execute command");
-    if output.status.success() {
-        let output_str = str::from_utf8(&output.stdout).unwrap_or("Invalid UTF-8");
-        for line in output_str.lines() {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() == 3 {
-                let name = parts[0].to_string();
-                let state = parts[1].to_string();
-                let status = parts[2].to_string();
-                container_map.insert(name, (state, status));
-            }
+    let output_str = run_command(Command::new("docker").arg("ps").arg("--format").arg("{{.Names}}:{{.State}}:{{.Status}}"))?;
+    for line in output_str.lines() {
+        let parts: Vec<&str> = line.split(':').collect();
+        if parts.len() == 3 {
+            let name = parts[0].to_string();
+            let state = parts[1].to_string();
+            let status = parts[2].to_string();
+            container_map.insert(name, (state, status));
         }
     }
-    else {
-        let error_str = str::from_utf8(&output.stderr).unwrap_or("Invalid UTF-8");
-        eprintln!("Error: {}", error_str);
-    }
 
     let mut dockers: Vec<Docker> = Vec::new();
     for container in containers {
@@ -332,75 +696,80 @@ pub fn get_docker(containers: &Vec<SysDocker>) -> Vec<Docker> {
             });
         }
     }
-    dockers
+    Ok(dockers)
 }
 
-fn get_disk_usage(disk_config: &SysDisk) -> Result<DiskInfo, String> {
-    let path_c = CString::new(disk_config.path.as_str()).map_err(|e| e.to_string())?;
+pub fn get_subvols(disk_config: &SysDisk) -> Result<Vec<SubvolInfo>, CollectError> {
+    let mut subvol: Vec<SubvolInfo> = Vec::new();
+    let output_str =
+        run_command(Command::new("btrfs").arg("--format").arg("json").arg("qgroup").arg("show").arg(disk_config.path.as_str()))?;
+    let parsed_json: Value =
+        serde_json::from_str(&output_str).map_err(|e| CollectError::Failed(format!("btrfs: invalid JSON: {}", e)))?;
+    let mut path_reference_map: HashMap<String, u64> = HashMap::new();
+    if let Some(qgroup_show) = parsed_json.get("qgroup-show").and_then(|v| v.as_array()) {
+        for entry in qgroup_show {
+            if let (Some(path), Some(referenced)) =
+                (entry.get("path").and_then(|v| v.as_str()), entry.get("referenced").and_then(|v| v.as_u64()))
+            {
+                path_reference_map.insert(path.to_string(), referenced);
+            }
+        }
+    }
+    for subvol_name in &disk_config.subvol {
+        if let Some(referenced) = path_reference_map.get(subvol_name) {
+            subvol.push(SubvolInfo { name: subvol_name.to_string(), used: *referenced });
+        }
+    }
+    Ok(subvol)
+}
+
+pub fn get_disk_usage(disk_config: &SysDisk, threshold: &Threshold) -> Result<(DiskInfo, Option<CollectError>), CollectError> {
+    let path_c = CString::new(disk_config.path.as_str()).map_err(|e| CollectError::Failed(e.to_string()))?;
     let mut stat: statvfs_t = unsafe { mem::zeroed() };
 
     let ret = unsafe { statvfs(path_c.as_ptr() as *const c_char, &mut stat) };
     if ret != 0 {
-        return Err("Failed to get disk usage".to_string());
+        let e = io::Error::last_os_error();
+        return Err(CollectError::Failed(format!("{}: {}", disk_config.path, e)));
     }
 
     let name = if disk_config.display != "none" { &disk_config.display } else { &disk_config.path };
     let total = stat.f_blocks * stat.f_frsize as u64;
     let used = (stat.f_blocks - stat.f_bfree) * stat.f_frsize as u64;
-    let mut subvol: Vec<(String, u64)> = Vec::new();
-    if !disk_config.subvol.is_empty() {
-        let output = Command::new("btrfs")
-            .arg("--format")
-            .arg("json")
-            .arg("qgroup")
-            .arg("show")
-            .arg(disk_config.path.as_str())
-            .output()
-            .expect("Failed to execute command");
-        if output.status.success() {
-            let output_str = str::from_utf8(&output.stdout).unwrap_or("Invalid UTF-8");
-
-            let parsed_json: Value = serde_json::from_str(output_str).expect("Failed to parse JSON");
-            let mut path_reference_map: HashMap<String, u64> = HashMap::new();
-            if let Some(qgroup_show) = parsed_json.get("qgroup-show").and_then(|v| v.as_array()) {
-                for entry in qgroup_show {
-                    if let (Some(path), Some(referenced)) =
-                        (entry.get("path").and_then(|v| v.as_str()), entry.get("referenced").and_then(|v| v.as_u64()))
-                    {
-                        path_reference_map.insert(path.to_string(), referenced);
-                    }
-                }
-            }
-            for subvol_name in &disk_config.subvol {
-                if let Some(referenced) = path_reference_map.get(subvol_name) {
-                    subvol.push((subvol_name.to_string(), *referenced));
-                }
-            }
-        }
-        else {
-            let error_str = str::from_utf8(&output.stderr).unwrap_or("Invalid UTF-8");
-            eprintln!("Error: {}", error_str);
-        }
+    let (subvol, error) = if disk_config.subvol.is_empty() {
+        (Vec::new(), None)
     }
-    Ok(DiskInfo { name: name.to_string(), total, used, subvol })
+    else {
+        match get_subvols(disk_config) {
+            Ok(subvol) => (subvol, None),
+            Err(e) => (Vec::new(), Some(e))
+        }
+    };
+    let level = disk_config.threshold(threshold).level(percent(used, total));
+    Ok((DiskInfo { name: name.to_string(), total, used, subvol, level }, error))
 }
 
-fn get_disks(disks_config: &Vec<SysDisk>) -> Vec<DiskInfo> {
+pub fn get_disks(disks_config: &Vec<SysDisk>, threshold: &Threshold) -> (Vec<DiskInfo>, Option<CollectError>) {
     let mut disks: Vec<DiskInfo> = Vec::new();
+    let mut error = None;
     for sysdisk in disks_config {
-        if let Ok(disk_info) = get_disk_usage(sysdisk) {
-            disks.push(disk_info);
+        match get_disk_usage(sysdisk, threshold) {
+            Ok((disk_info, subvol_error)) => {
+                disks.push(disk_info);
+                if let Some(e) = subvol_error {
+                    error.get_or_insert(e);
+                }
+            }
+            Err(e) => {
+                error.get_or_insert(e);
+            }
         }
     }
-    disks
+    (disks, error)
 }
 
-fn get_vm(vm_config: &SysVm) -> io::Result<VmInfo> {
-    let output = Command::new("virsh").arg("dominfo").arg(vm_config.name.clone()).output()?;
-    if !output.status.success() {
-        return Err(io::Error::new(io::ErrorKind::Other, String::from_utf8_lossy(&output.stderr).to_string()));
-    }
-    let output_str = String::from_utf8_lossy(&output.stdout);
+pub fn get_vm(vm_config: &SysVm, threshold: &StateThreshold) -> Result<VmInfo, CollectError> {
+    let output_str = run_command(Command::new("virsh").arg("dominfo").arg(vm_config.name.clone()))?;
     let mut info_map = HashMap::new();
     for line in output_str.lines() {
         if let Some((key, value)) = line.split_once(":") {
@@ -420,29 +789,36 @@ fn get_vm(vm_config: &SysVm) -> io::Result<VmInfo> {
     let used_mem = str2byte(info_map.get("used memory").unwrap_or(&"0".to_string()));
     let total_mem = str2byte(info_map.get("max memory").unwrap_or(&"0".to_string()));
     let autostart = info_map.get("autostart").unwrap_or(&"Unknown".to_string()).clone();
-    Ok(VmInfo { name, state, cpus, used_mem, total_mem, autostart })
+    let level = threshold.level(&state);
+    Ok(VmInfo { name, state, cpus, used_mem, total_mem, autostart, level })
 }
 
-fn get_vms(vms_config: &Vec<SysVm>) -> Vec<VmInfo> {
+pub fn get_vms(vms_config: &Vec<SysVm>, threshold: &StateThreshold) -> (Vec<VmInfo>, Option<CollectError>) {
     let mut vms: Vec<VmInfo> = Vec::new();
+    let mut error = None;
     for vm in vms_config {
-        if let Ok(vm_info) = get_vm(vm) {
-            vms.push(vm_info);
+        match get_vm(vm, threshold) {
+            Ok(vm_info) => vms.push(vm_info),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
         }
     }
-    vms
+    (vms, error)
 }
 
-fn get_nvidia_smi(gpu_config: &SysGpu) -> io::Result<GpuInfo> {
-    let output = Command::new("nvidia-smi")
-        .arg("--query-gpu=memory.used,memory.total,temperature.gpu")
-        .arg("--format=csv,noheader,nounits")
-        .output()?;
-    if !output.status.success() {
-        return Err(io::Error::new(io::ErrorKind::Other, String::from_utf8_lossy(&output.stderr).to_string()));
-    }
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let mut gpu_info = GpuInfo { mem_name: "VRAM".to_string(), temp_name: "GPU Core".to_string(), temp: 0, used_vram: 0, total_vram: 0 };
+pub fn get_nvidia_smi(gpu_config: &SysGpu) -> Result<GpuInfo, CollectError> {
+    let output_str = run_command(
+        Command::new("nvidia-smi").arg("--query-gpu=memory.used,memory.total,temperature.gpu").arg("--format=csv,noheader,nounits")
+    )?;
+    let mut gpu_info = GpuInfo {
+        mem_name:   "VRAM".to_string(),
+        temp_name:  "GPU Core".to_string(),
+        temp:       0,
+        used_vram:  0,
+        total_vram: 0,
+        temp_level: Level::Ok
+    };
     let lines: Vec<&str> = output_str.lines().collect();
     if let Some(line) = lines.get(gpu_config.command.parse::<usize>().unwrap_or(0)) {
         let parts: Vec<&str> = line.split(',').collect();
@@ -457,40 +833,90 @@ fn get_nvidia_smi(gpu_config: &SysGpu) -> io::Result<GpuInfo> {
     Ok(gpu_info)
 }
 
-fn get_gpus(gpus_config: &Vec<SysGpu>) -> Vec<GpuInfo> {
+pub fn get_gpus(gpus_config: &Vec<SysGpu>) -> (Vec<GpuInfo>, Option<CollectError>) {
     let mut gpus: Vec<GpuInfo> = Vec::new();
+    let mut error = None;
     for gpu in gpus_config {
         if gpu.command == "nvidia-smi" {
-            if let Ok(gpu_info) = get_nvidia_smi(gpu) {
-                gpus.push(gpu_info);
+            match get_nvidia_smi(gpu) {
+                Ok(gpu_info) => gpus.push(gpu_info),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
             }
         }
     }
-    gpus
+    (gpus, error)
+}
+
+// Runs a quick collector on the calling thread, if its section is enabled.
+fn collect_if<C: Collector>(enabled: bool, collector: C, status: &mut BTreeMap<String, SectionStatus>) -> C::Output {
+    if !enabled {
+        return C::Output::default();
+    }
+    let (value, section_status) = collector.collect();
+    status.insert(collector.name().to_string(), section_status);
+    value
+}
+
+pub(crate) fn wait<T: Default>(pending: Pending<T>, start: Instant, status: &mut BTreeMap<String, SectionStatus>) -> T {
+    let name = pending.name().to_string();
+    let (value, section_status) = pending.wait(start);
+    status.insert(name, section_status);
+    value
 }
 
 impl SystemStats {
     pub fn new(config: &Config) -> Self {
-        let memory = get_memory().unwrap_or_else(|e| {
-            eprintln!("Failed to get memory information: {}", e);
-            std::process::exit(1);
-        });
-        let load_avg = get_load();
-        let uptime = get_uptime();
-        let last_login = get_last_login();
-
-        let services = get_service_all(&config.systemctl);
-        let dockers = get_docker(&config.docker);
-        let cpu_temp = get_cpu_temp(&config.cputemp).unwrap_or_default();
-        let disks = get_disks(&config.disk);
-        let vms = get_vms(&config.vm);
-        let gpus = get_gpus(&config.gpu);
-
-        Self { memory, load_avg, cpu_temp, uptime, last_login, disks, services, dockers, vms, gpus }
+        let start = Instant::now();
+
+        // Sections that are disabled or have nothing configured are not collected and get no status.
+        let last_login = config.lastlogin.then(|| spawn(LastLoginCollector, &config.timeouts));
+        let services = (!config.systemctl.is_empty()).then(|| spawn(SystemdCollector::new(config), &config.timeouts));
+        let dockers = (!config.docker.is_empty()).then(|| spawn(DockerCollector::new(config), &config.timeouts));
+        let cpu_temp = (config.cputemp != "none").then(|| spawn(TempCollector::new(config), &config.timeouts));
+        let disks = (!config.disk.is_empty()).then(|| spawn(DiskCollector::new(config), &config.timeouts));
+        let vms = (!config.vm.is_empty()).then(|| spawn(LibvirtCollector::new(config), &config.timeouts));
+        let gpus = (!config.gpu.is_empty()).then(|| spawn(NvidiaCollector::new(config), &config.timeouts));
+        let cpu = config.cpu.enabled.then(|| spawn(CpuCollector::new(config), &config.timeouts));
+        let cpu_info = config.cpuinfo.then(|| spawn(CpuInfoCollector::new(config), &config.timeouts));
+        let pressure = config.psi.enabled.then(|| spawn(PressureCollector::new(config), &config.timeouts));
+        let plugins: Vec<_> = config.plugin.iter().map(|plugin| spawn(PluginCollector::new(plugin), &config.timeouts)).collect();
+
+        let mut status = BTreeMap::new();
+        let identity = collect_if(config.header.enabled, IdentityCollector, &mut status);
+        let memory = collect_if(config.memory, MemoryCollector::new(config), &mut status);
+        let load_avg = collect_if(config.cpuload, LoadCollector::new(config), &mut status);
+        let uptime = collect_if(config.uptime, UptimeCollector, &mut status);
+
+        let cpu = cpu.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let cpu_info = cpu_info.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let pressure = pressure.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let last_login = last_login.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let services = services.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let dockers = dockers.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let cpu_temp = cpu_temp.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let disks = disks.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let vms = vms.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let gpus = gpus.map(|pending| wait(pending, start, &mut status)).unwrap_or_default();
+        let plugins = plugins
+            .into_iter()
+            .zip(&config.plugin)
+            .map(|(pending, plugin)| {
+                let section = wait(pending, start, &mut status);
+                if section.name.is_empty() { PluginSection::new(plugin) } else { section }
+            })
+            .collect();
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+        Self { identity, memory, load_avg, cpu, cpu_info, pressure, cpu_temp, uptime, last_login, disks, services, dockers, vms, gpus, plugins, status, timestamp, config: config.identity.clone(), cached: false }
     }
 
-    // pub fn update(&mut self) -> &mut Self {
-    //     // self.sys.refresh_all();
-    //     self
-    // }
+    pub fn collected(&self, section: &str) -> bool { self.status.get(section).is_some_and(|status| status.is_ok()) }
+
+    pub fn update(&mut self, config: &Config) -> &mut Self {
+        *self = Self::new(config);
+        self
+    }
 }
@@ -0,0 +1,57 @@
+// run  := cargo run -- -c motd.toml
+// dir  := .
+// kid  :=
+
+// Jinja-style output templates replacing the built-in layout, see the README for the variables.
+
+use std::fs;
+
+use minijinja::{Environment, Value};
+
+use crate::cache::snapshot_age;
+use crate::load_config::Config;
+use crate::plain_text::{generate_sections, to_level};
+use crate::system_stats::SystemStats;
+use crate::thresholds::{percent, Level};
+use crate::utils::{byte2str, s2time, visible_width};
+
+fn parse_level(level: &str) -> Level {
+    match level {
+        "warn" => Level::Warn,
+        "critical" => Level::Critical,
+        _ => Level::Ok
+    }
+}
+
+fn pad(text: &str, width: usize) -> String { " ".repeat(width.saturating_sub(visible_width(text))) }
+
+fn environment<'a>() -> Environment<'a> {
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.add_filter("bytes", |n: u64| byte2str(n, true));
+    env.add_filter("bytes_si", |n: u64| byte2str(n, false));
+    env.add_filter("duration", |seconds: u64| s2time(seconds));
+    env.add_filter("level", |text: String, level: String| to_level(&text, parse_level(&level)));
+    env.add_filter("ljust", |text: String, width: usize| format!("{}{}", text, pad(&text, width)));
+    env.add_filter("rjust", |text: String, width: usize| format!("{}{}", pad(&text, width), text));
+    env.add_function("percent", |used: u64, total: u64| percent(used, total));
+    env
+}
+
+pub fn generate_template(config: &Config, stats: &SystemStats) -> Result<String, String> {
+    let source = fs::read_to_string(&config.template).map_err(|e| format!("Failed to read template {}: {}", config.template, e))?;
+    let mut context = serde_json::to_value(stats).map_err(|e| e.to_string())?;
+    let sections: Vec<_> = generate_sections(config, stats)
+        .into_iter()
+        .map(|(title, text)| serde_json::json!({ "title": title, "text": text }))
+        .collect();
+    context["sections"] = sections.into();
+    context["snapshot_age"] = snapshot_age(stats).into();
+
+    let env = environment();
+    env.template_from_named_str(&config.template, &source)
+        .and_then(|template| template.render(Value::from_serialize(&context)))
+        .map_err(|e| format!("Failed to render template: {}", e))
+}
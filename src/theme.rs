@@ -0,0 +1,352 @@
+// run  := cargo run -- -c motd.toml
+// dir  := .
+// kid  :=
+
+// Themes: styles for each part of the text output, see the README for the keys and syntax.
+
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+use crate::load_config::{ThemeConfig, ThemeStyles};
+use crate::thresholds::Level;
+
+const DEFAULT: &str = r##"
+warn = "yellow"
+critical = "bold red"
+heading = "bold"
+bar = "green"
+"##;
+
+const SOLARIZED: &str = r##"
+label = "#268bd2"
+value = "#93a1a1"
+ok = "#859900"
+warn = "#b58900"
+critical = "bold #dc322f"
+heading = "bold #2aa198"
+bar = "#859900"
+bar_empty = "#586e75"
+"##;
+
+const MONOCHROME: &str = r##"
+warn = "bold"
+critical = "bold reverse"
+heading = "bold underline"
+bar = "bold"
+bar_empty = "dim"
+"##;
+
+const HIGH_CONTRAST: &str = r##"
+label = "bold bright-white"
+value = "bright-white"
+ok = "bright-green"
+warn = "bold black on bright-yellow"
+critical = "bold bright-white on red"
+heading = "bold underline bright-cyan"
+bar = "bright-green"
+bar_empty = "bright-black"
+"##;
+
+// The xterm defaults, used to map 256 and true colours down to 16.
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255)
+];
+
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorMode {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor
+}
+
+impl ColorMode {
+    fn from_term() -> ColorMode {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorMode::TrueColor
+        }
+        else if term.contains("256") {
+            ColorMode::Ansi256
+        }
+        else {
+            ColorMode::Ansi16
+        }
+    }
+
+    pub fn detect(setting: &str) -> ColorMode {
+        match setting {
+            "never" => ColorMode::None,
+            "always" => ColorMode::from_term(),
+            "16" => ColorMode::Ansi16,
+            "256" => ColorMode::Ansi256,
+            "truecolor" => ColorMode::TrueColor,
+            _ => {
+                let no_color = env::var("NO_COLOR").is_ok_and(|v| !v.is_empty());
+                if no_color || !std::io::stdout().is_terminal() || env::var("TERM").is_ok_and(|t| t == "dumb") {
+                    ColorMode::None
+                }
+                else {
+                    ColorMode::from_term()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Color {
+    Basic(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8)
+}
+
+fn indexed_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => BASIC_RGB[n as usize],
+        16..=231 => {
+            let n = n - 16;
+            (CUBE_LEVELS[(n / 36) as usize], CUBE_LEVELS[(n / 6 % 6) as usize], CUBE_LEVELS[(n % 6) as usize])
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn nearest_basic((r, g, b): (u8, u8, u8)) -> u8 {
+    let distance = |(br, bg, bb): &(u8, u8, u8)| {
+        let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+        d(r, *br) + d(g, *bg) + d(b, *bb)
+    };
+    (0..16).min_by_key(|i| distance(&BASIC_RGB[*i as usize])).unwrap_or(7)
+}
+
+fn nearest_indexed(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..=7 => 16,
+            248.. => 231,
+            _ => 232 + (r - 8) / 10
+        };
+    }
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v - 35) / 40
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+impl Color {
+    fn parse(spec: &str) -> Option<Color> {
+        if let Some(hex) = spec.strip_prefix('#') {
+            let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)?;
+            return Some(Color::Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8));
+        }
+        if let Ok(n) = spec.parse::<u8>() {
+            return Some(Color::Indexed(n));
+        }
+        let (name, bright) = match spec.strip_prefix("bright-").or_else(|| spec.strip_prefix("bright_")) {
+            Some(name) => (name, 8),
+            None => (spec, 0)
+        };
+        match name {
+            "gray" | "grey" => Some(Color::Basic(8)),
+            _ => COLOR_NAMES.iter().position(|n| *n == name).map(|i| Color::Basic(i as u8 + bright))
+        }
+    }
+
+    // SGR parameters for this colour as foreground (base 30) or background (base 40).
+    fn code(self, mode: ColorMode, base: u8) -> String {
+        let basic = |n: u8| if n < 8 { format!("{}", base + n) } else { format!("{}", base + 60 + n - 8) };
+        match (self, mode) {
+            (Color::Basic(n), _) => basic(n),
+            (Color::Indexed(n), ColorMode::Ansi16) => basic(nearest_basic(indexed_rgb(n))),
+            (Color::Indexed(n), _) => format!("{};5;{}", base + 8, n),
+            (Color::Rgb(r, g, b), ColorMode::TrueColor) => format!("{};2;{};{};{}", base + 8, r, g, b),
+            (Color::Rgb(r, g, b), ColorMode::Ansi256) => format!("{};5;{}", base + 8, nearest_indexed(r, g, b)),
+            (Color::Rgb(r, g, b), _) => basic(nearest_basic((r, g, b)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Style {
+    attributes: Vec<u8>,
+    fg:         Option<Color>,
+    bg:         Option<Color>
+}
+
+impl Style {
+    pub fn parse(spec: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+        let mut background = false;
+        for token in spec.split_whitespace() {
+            let attribute = match token {
+                "bold" => Some(1),
+                "dim" => Some(2),
+                "italic" => Some(3),
+                "underline" => Some(4),
+                "reverse" => Some(7),
+                _ => None
+            };
+            if let Some(attribute) = attribute {
+                style.attributes.push(attribute);
+            }
+            else if token == "on" {
+                background = true;
+            }
+            else {
+                let color = Color::parse(token).ok_or_else(|| format!("unknown colour or attribute '{}' in \"{}\"", token, spec))?;
+                if background {
+                    style.bg = Some(color);
+                }
+                else {
+                    style.fg = Some(color);
+                }
+            }
+        }
+        Ok(style)
+    }
+
+    fn sgr(&self, mode: ColorMode) -> String {
+        if mode == ColorMode::None {
+            return String::new();
+        }
+        let mut codes: Vec<String> = self.attributes.iter().map(|a| a.to_string()).collect();
+        codes.extend(self.fg.map(|c| c.code(mode, 30)));
+        codes.extend(self.bg.map(|c| c.code(mode, 40)));
+        if codes.is_empty() {
+            String::new()
+        }
+        else {
+            format!("\x1b[{}m", codes.join(";"))
+        }
+    }
+}
+
+pub struct Theme {
+    pub mode:  ColorMode,
+    label:     String,
+    value:     String,
+    ok:        String,
+    warn:      String,
+    critical:  String,
+    heading:   String,
+    bar:       String,
+    bar_empty: String
+}
+
+fn builtin(name: &str) -> Option<&'static str> {
+    match name {
+        "default" => Some(DEFAULT),
+        "solarized" => Some(SOLARIZED),
+        "monochrome" => Some(MONOCHROME),
+        "high-contrast" => Some(HIGH_CONTRAST),
+        _ => None
+    }
+}
+
+fn paint(prefix: &str, text: &str) -> String {
+    if prefix.is_empty() || text.is_empty() {
+        text.to_string()
+    }
+    else {
+        format!("{}{}\x1b[0m", prefix, text)
+    }
+}
+
+impl Theme {
+    pub fn load(config: &ThemeConfig, color: Option<&str>) -> Result<Theme, String> {
+        let content = match builtin(&config.name) {
+            Some(content) => content.to_string(),
+            None => fs::read_to_string(&config.name).map_err(|e| format!("Failed to read theme {}: {}", config.name, e))?
+        };
+        let base: ThemeStyles = toml::from_str(&content).map_err(|e| format!("Failed to parse theme {}: {}", config.name, e))?;
+        let mode = ColorMode::detect(color.unwrap_or(&config.color));
+        let style = |own: &Option<String>, base: &Option<String>| -> Result<String, String> {
+            let spec = own.as_ref().or(base.as_ref()).map(String::as_str).unwrap_or("");
+            Ok(Style::parse(spec).map_err(|e| format!("Invalid theme style: {}", e))?.sgr(mode))
+        };
+        let own = &config.styles;
+        Ok(Theme {
+            mode,
+            label: style(&own.label, &base.label)?,
+            value: style(&own.value, &base.value)?,
+            ok: style(&own.ok, &base.ok)?,
+            warn: style(&own.warn, &base.warn)?,
+            critical: style(&own.critical, &base.critical)?,
+            heading: style(&own.heading, &base.heading)?,
+            bar: style(&own.bar, &base.bar)?,
+            bar_empty: style(&own.bar_empty, &base.bar_empty)?
+        })
+    }
+
+    pub fn level(&self, text: &str, level: Level) -> String {
+        match level {
+            Level::Ok => paint(&self.ok, text),
+            Level::Warn => paint(&self.warn, text),
+            Level::Critical => paint(&self.critical, text)
+        }
+    }
+
+    pub fn heading(&self, text: &str) -> String { paint(&self.heading, text) }
+
+    pub fn bold(&self, text: &str) -> String { paint(if self.mode == ColorMode::None { "" } else { "\x1b[1m" }, text) }
+
+    // Without a bar_empty style the whole bar takes the style of its filled part.
+    pub fn bar(&self, filled: &str, empty: &str, level: Level) -> String {
+        let fill = |text: &str| if level == Level::Ok { paint(&self.bar, text) } else { self.level(text, level) };
+        if self.bar_empty.is_empty() {
+            fill(&format!("{}{}", filled, empty))
+        }
+        else {
+            fill(filled) + &paint(&self.bar_empty, empty)
+        }
+    }
+
+    pub fn styles_rows(&self) -> bool { !self.label.is_empty() || !self.value.is_empty() }
+
+    pub fn label(&self, text: &str) -> String { paint(&self.label, text) }
+
+    // Values may already contain coloured parts; the value style is restored after each of them.
+    pub fn value(&self, text: &str) -> String {
+        if self.value.is_empty() {
+            return text.to_string();
+        }
+        paint(&self.value, &text.replace("\x1b[0m", &format!("\x1b[0m{}", self.value)))
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+pub fn init(theme: Theme) { let _ = THEME.set(theme); }
+
+// The theme set by `init`, or the default theme with detected colour support.
+pub fn current() -> &'static Theme {
+    THEME.get_or_init(|| Theme::load(&ThemeConfig::default(), None).expect("built-in theme is valid"))
+}
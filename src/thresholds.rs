@@ -0,0 +1,60 @@
+// run  := cargo run --
+// dir  := .
+// kid  :=
+
+use serde::{Deserialize, Serialize};
+
+use crate::load_config::{StateThreshold, SysDisk, Threshold};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Ok,
+    Warn,
+    Critical
+}
+
+impl Threshold {
+    pub fn level(&self, value: f64) -> Level {
+        if value >= self.critical {
+            Level::Critical
+        }
+        else if value >= self.warn {
+            Level::Warn
+        }
+        else {
+            Level::Ok
+        }
+    }
+}
+
+impl StateThreshold {
+    pub fn level(&self, state: &str) -> Level {
+        let state = state.trim();
+        if self.critical.iter().any(|s| s.eq_ignore_ascii_case(state)) {
+            Level::Critical
+        }
+        else if self.warn.iter().any(|s| s.eq_ignore_ascii_case(state)) {
+            Level::Warn
+        }
+        else {
+            Level::Ok
+        }
+    }
+}
+
+impl SysDisk {
+    pub fn threshold(&self, default: &Threshold) -> Threshold {
+        Threshold { warn: self.warn.unwrap_or(default.warn), critical: self.critical.unwrap_or(default.critical) }
+    }
+}
+
+pub fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    }
+    else {
+        used as f64 * 100.0 / total as f64
+    }
+}
@@ -1,4 +1,9 @@
 use byte_unit::{Byte, UnitType};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use humantime::format_duration;
 
@@ -9,9 +14,75 @@ pub fn byte2str(num: u64, binary: bool) -> String {
 }
 
 pub fn str2byte(s: &str) -> u64 {
-    Byte::parse_str(s, false).unwrap().as_u64()
+    Byte::parse_str(s, false).map(|b| b.as_u64()).unwrap_or(0)
 }
 
 pub fn s2time(seconds: u64) -> String {
     format_duration(Duration::from_secs(seconds)).to_string()
 }
+
+pub fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in s.chars() {
+        if in_escape {
+            in_escape = c != 'm';
+        }
+        else if c == '\x1b' {
+            in_escape = true;
+        }
+        else {
+            width += 1;
+        }
+    }
+    width
+}
+
+pub fn term_width() -> usize {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse::<usize>().ok()) {
+        return columns;
+    }
+    if std::io::stdout().is_terminal() {
+        if let Ok((cols, _)) = crossterm::terminal::size() {
+            return cols as usize;
+        }
+    }
+    80
+}
+
+// Shorten `s` to at most `width` characters, marking the cut with an ellipsis.
+pub fn ellipsize(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_string();
+    }
+    let ellipsis = if utf8_locale() { "…" } else { "..." };
+    let keep = width.saturating_sub(ellipsis.chars().count());
+    s.chars().take(keep).collect::<String>() + ellipsis
+}
+
+pub fn utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| value.to_uppercase().replace('-', "").contains("UTF8"))
+        .unwrap_or(false)
+}
+
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Write to a temporary file next to `path` and rename it into place, so readers never see a
+// partially written file. The temporary name is unique per process and call, so concurrent
+// writers (e.g. the timer and a login) never write into each other's file.
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.{}.{}.tmp", name, process::id(), TMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let result = fs::write(&tmp, content).and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}